@@ -0,0 +1,197 @@
+use super::evaluator::{self, Settings};
+use super::parser::ParseTree;
+use super::value::Value;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+// The most terms a sum or product may add up.
+const MAX_TERMS: i128 = 1_000_000;
+
+// The most times an integral may evaluate its integrand.
+const MAX_EVALUATIONS: usize = 100_000;
+
+// How far an interval may be halved before an integral gives up.
+const MAX_DEPTH: usize = 50;
+
+const TOLERANCE: f64 = 1e-10;
+
+// `sum(k^2, k, 1, 10)` is a series by its shape alone: four arguments with
+// a variable second. `sum(5, k, 1, 10)` is 50 whatever `k` holds, and other
+// calls like `sum(1, 2, 3, 4)` still add their values.
+pub fn is_series(name: &str, arguments: &[ParseTree]) -> bool {
+    name == "sum" && matches!(arguments, [_, ParseTree::Var(_), _, _])
+}
+
+// The expression with the variable bound to each value in turn. Every
+// evaluation gets a copy of the symbol table, so the variable and anything
+// the expression assigns stay out of the session.
+fn scoped<'a>(f: &'a ParseTree, x: &'a str, symbols: &'a HashMap<String, Value>, settings: &'a Settings) -> impl Fn(Value) -> Result<Value, String> + 'a {
+    move |t| {
+        let mut scope = symbols.clone();
+        scope.insert(x.to_string(), t);
+        evaluator::evaluate(Ok(Box::new(f.clone())), scope, settings).0
+    }
+}
+
+fn bound(tree: ParseTree, symbols: &HashMap<String, Value>, settings: &Settings) -> Result<Value, String> {
+    evaluator::evaluate(Ok(Box::new(tree)), symbols.clone(), settings).0
+}
+
+// `sum(expr, k, a, b)` and `product(expr, k, a, b)` over the integers a to b.
+// An empty range gives 0 or 1.
+pub fn series(name: &str, arguments: Vec<ParseTree>, symbols: &HashMap<String, Value>, settings: &Settings) -> Result<Value, String> {
+    let (f, k, a, b) = match <[ParseTree; 4]>::try_from(arguments) {
+        Ok([f, ParseTree::Var(k), a, b]) => (f, k, a, b),
+        _ => return Err(format!("{} takes an expression, a variable and two bounds, like {}(k^2, k, 1, 10)", name, name))
+    };
+    let (a, b) = match (bound(a, symbols, settings)?, bound(b, symbols, settings)?) {
+        (Value::Int(a), Value::Int(b)) => (a, b),
+        _ => return Err(format!("The bounds of a {} must be integers", name))
+    };
+    if b.saturating_sub(a) >= MAX_TERMS {
+        return Err(format!("A {} may have at most {} terms", name, MAX_TERMS));
+    }
+    let term = scoped(&f, &k, symbols, settings);
+    let combine: fn(Value, Value) -> Result<Value, String> = match name {
+        "product" => Value::mul,
+        _ => Value::add
+    };
+    let mut terms = (a..=b).map(|i| term(Value::Int(i)));
+    // Starts from the first term when there is one, so quantities work.
+    match terms.next() {
+        Some(first) => terms.try_fold(first?, |total, x| combine(total, x?)),
+        None => Ok(Value::Int(if name == "product" { 1 } else { 0 }))
+    }
+}
+
+// `integrate(expr, x, a, b)` by adaptive Simpson's rule, which halves each
+// interval until the two halves agree with the whole.
+pub fn integrate(arguments: Vec<ParseTree>, symbols: &HashMap<String, Value>, settings: &Settings) -> Result<Value, String> {
+    let (f, x, a, b) = match <[ParseTree; 4]>::try_from(arguments) {
+        Ok([f, ParseTree::Var(x), a, b]) => (f, x, a, b),
+        _ => return Err(String::from("integrate takes an expression, a variable and two bounds, like integrate(x^2, x, 0, 1)"))
+    };
+    let (a, b) = match (bound(a, symbols, settings)?, bound(b, symbols, settings)?) {
+        (a @ Value::Int(_), b) | (a @ Value::Real(_), b) | (a @ Value::Decimal(_), b) if matches!(b, Value::Int(_) | Value::Real(_) | Value::Decimal(_)) => (a.to_f64(), b.to_f64()),
+        _ => return Err(String::from("The bounds of an integral must be real numbers"))
+    };
+    if !a.is_finite() || !b.is_finite() {
+        return Err(String::from("The bounds of an integral must be finite"));
+    }
+    let integrand = scoped(&f, &x, symbols, settings);
+    let mut evaluations = 0;
+    let mut value = |t: f64| {
+        evaluations += 1;
+        if evaluations > MAX_EVALUATIONS {
+            return Err(format!("The integral did not settle within {} evaluations", MAX_EVALUATIONS));
+        }
+        match integrand(Value::Real(t))? {
+            y @ Value::Int(_) | y @ Value::Real(_) | y @ Value::Decimal(_) if y.to_f64().is_finite() => Ok(y.to_f64()),
+            Value::Int(_) | Value::Real(_) | Value::Decimal(_) => Err(String::from("The integral does not converge")),
+            _ => Err(String::from("Only real functions can be integrated"))
+        }
+    };
+    let m = (a + b) / 2.0;
+    let (fa, fm, fb) = (value(a)?, value(m)?, value(b)?);
+    let whole = (b - a) / 6.0 * (fa + 4.0 * fm + fb);
+    let area = simpson(&mut value, (a, fa), (m, fm), (b, fb), whole, TOLERANCE, MAX_DEPTH)?;
+    Ok(Value::Real(area))
+}
+
+fn simpson(value: &mut impl FnMut(f64) -> Result<f64, String>, (a, fa): (f64, f64), (m, fm): (f64, f64), (b, fb): (f64, f64), whole: f64, tolerance: f64, depth: usize) -> Result<f64, String> {
+    let (left, right) = ((a + m) / 2.0, (m + b) / 2.0);
+    let (fl, fr) = (value(left)?, value(right)?);
+    let first = (m - a) / 6.0 * (fa + 4.0 * fl + fm);
+    let second = (b - m) / 6.0 * (fm + 4.0 * fr + fb);
+    let error = first + second - whole;
+    // Past the precision of a float the halves can only agree to rounding.
+    if error.abs() <= 15.0 * tolerance || error.abs() <= f64::EPSILON * (first + second).abs() {
+        return Ok(first + second + error / 15.0);
+    }
+    if depth == 0 {
+        return Err(String::from("The integral does not converge"));
+    }
+    Ok(simpson(value, (a, fa), (left, fl), (m, fm), first, tolerance / 2.0, depth - 1)?
+        + simpson(value, (m, fm), (right, fr), (b, fb), second, tolerance / 2.0, depth - 1)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lexer::Tokens;
+    use crate::parser::Parser;
+
+    fn call(line: &str) -> Result<Value, String> {
+        call_with(line, &Settings::default())
+    }
+
+    fn call_with(line: &str, settings: &Settings) -> Result<Value, String> {
+        match parse(line) {
+            (name, arguments) if name == "integrate" => integrate(arguments, &HashMap::new(), settings),
+            (name, arguments) => series(&name, arguments, &HashMap::new(), settings)
+        }
+    }
+
+    fn parse(line: &str) -> (String, Vec<ParseTree>) {
+        let mut tokenizer = Tokens::new(line.chars());
+        tokenizer.tokenize();
+        let mut parser = Parser::new();
+        parser.parse(tokenizer.tokens);
+        match *parser.tree.unwrap() {
+            ParseTree::Call(name, arguments) => (name, arguments),
+            tree => panic!("not a call: {:?}", tree)
+        }
+    }
+
+    fn shaped(line: &str) -> bool {
+        let (name, arguments) = parse(line);
+        is_series(&name, &arguments)
+    }
+
+    fn close(value: Result<Value, String>, expected: f64) -> bool {
+        value.is_ok_and(|x| (x.to_f64() - expected).abs() < 1e-9)
+    }
+
+    #[test]
+    fn series_of_terms() {
+        assert_eq!(call("sum(k^2, k, 1, 10)"), Ok(Value::Int(385)));
+        assert_eq!(call("product(k, k, 1, 20)"), Ok(Value::Int(2432902008176640000)));
+        assert_eq!(call("sum(k, k, 5, 1)"), Ok(Value::Int(0)));
+        assert_eq!(call("product(k, k, 5, 1)"), Ok(Value::Int(1)));
+        assert!(close(call("sum(1.0/k^2, k, 1, 1000)"), 1.6439345666815615));
+        assert_eq!(call("sum(k, k, 1, 2.5)"), Err(String::from("The bounds of a sum must be integers")));
+        assert_eq!(call("sum(k, k, 0, 10000000)"), Err(String::from("A sum may have at most 1000000 terms")));
+        assert!(call("product(k, k, 1, 100)").is_err());
+        assert_eq!(call("sum(5, k, 1, 10)"), Ok(Value::Int(50)));
+        assert_eq!(call("product(2, k, 1, 10)"), Ok(Value::Int(1024)));
+    }
+
+    #[test]
+    fn series_shapes() {
+        assert!(shaped("sum(k^2, k, 1, 3)"));
+        assert!(shaped("sum(5, k, 1, 10)"));
+        assert!(!shaped("sum(1, 2, 3, 4)"));
+        assert!(!shaped("sum(k, k, 1)"));
+        assert!(!shaped("sum([1, 2])"));
+    }
+
+    #[test]
+    fn decimal_series() {
+        let settings = Settings { precision: Some(30), ..Settings::default() };
+        assert_eq!(call_with("sum(0.1, k, 1, 10)", &settings).map(|x| x.to_string()), Ok(String::from("1.0")));
+        assert_eq!(call_with("sum(1/k, k, 1, 4)", &settings).map(|x| x.to_string()), Ok(format!("2.08{}", "3".repeat(28))));
+        // Floats met along the way stay floats rather than errors.
+        assert!(call_with("integrate(sqrt(x), x, 0, 1)", &settings).is_ok_and(|x| (x.to_f64() - 2.0 / 3.0).abs() < 1e-9));
+    }
+
+    #[test]
+    fn integrals() {
+        assert!(close(call("integrate(x^2, x, 0, 3)"), 9.0));
+        assert!(close(call("integrate(exp(x), x, 0, 1)"), std::f64::consts::E - 1.0));
+        assert!(close(call("integrate(sqrt(x), x, 0, 1)"), 2.0 / 3.0));
+        assert!(close(call("integrate(x, x, 2, 0)"), -2.0));
+        assert_eq!(call("integrate(1/(x - 0.3), x, 0, 1)"), Err(String::from("The integral did not settle within 100000 evaluations")));
+        assert!(call("integrate(x, 1, 0, 1)").is_err());
+        assert_eq!(call("integrate(sqrt(x), x, -1, 1)"), Err(String::from("Only real functions can be integrated")));
+    }
+}
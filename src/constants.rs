@@ -0,0 +1,46 @@
+use super::units::Dimension;
+use super::value::Value;
+
+// Built-in constants, physical ones in coherent SI units. `h` is always the
+// hour, so Planck's constant is `h_P`.
+const CONSTANTS: &[(&str, f64, Dimension)] = &[
+    ("pi", std::f64::consts::PI, Dimension([0; 7])),
+    ("e", std::f64::consts::E, Dimension([0; 7])),
+    ("tau", std::f64::consts::TAU, Dimension([0; 7])),
+    ("phi", 1.618033988749895, Dimension([0; 7])),
+    ("inf", f64::INFINITY, Dimension([0; 7])),
+    ("c", 299792458.0, Dimension([1, 0, -1, 0, 0, 0, 0])),
+    ("G", 6.6743e-11, Dimension([3, -1, -2, 0, 0, 0, 0])),
+    ("h_P", 6.62607015e-34, Dimension([2, 1, -1, 0, 0, 0, 0])),
+    ("k_B", 1.380649e-23, Dimension([2, 1, -2, 0, -1, 0, 0])),
+    ("N_A", 6.02214076e23, Dimension([0, 0, 0, 0, 0, -1, 0])),
+];
+
+pub fn lookup(name: &str) -> Option<Value> {
+    CONSTANTS.iter().find(|(n, _, _)| *n == name).map(|(_, size, dimension)| match dimension.is_none() {
+        true => Value::Real(*size),
+        false => Value::Quantity(*size, *dimension)
+    })
+}
+
+pub fn names() -> Vec<&'static str> {
+    CONSTANTS.iter().map(|(name, _, _)| *name).collect()
+}
+
+pub fn read_only(name: &str) -> String {
+    format!("{} is a constant and cannot be changed", name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lookup_constants() {
+        assert_eq!(lookup("pi"), Some(Value::Real(std::f64::consts::PI)));
+        assert_eq!(lookup("inf").map(|x| x.to_f64()), Some(f64::INFINITY));
+        assert_eq!(lookup("c").map(|x| x.to_string()), Some(String::from("299792458.0 m/s")));
+        assert_eq!(lookup("h"), None);
+        assert_eq!(lookup("h_P").map(|x| x.to_string()), Some(String::from("6.62607015e-34 m^2*kg/s")));
+    }
+}
@@ -0,0 +1,553 @@
+use super::value::Value;
+use num_bigint::{BigInt, BigUint, Sign};
+use std::convert::TryFrom;
+use std::fmt;
+
+// The most significant digits decimal mode will keep.
+pub const MAX_PRECISION: u32 = 1000;
+
+// Results past 10^10000 or below 10^-10000 are refused, since lining such a
+// number up with an ordinary one would take that many digits.
+const MAX_EXPONENT: i64 = 10_000;
+
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub enum Rounding {
+    #[default]
+    HalfEven,
+    HalfUp,
+    TowardZero,
+    Floor,
+    Ceiling,
+}
+
+const ROUNDINGS: &[(&str, Rounding)] = &[
+    ("half-even", Rounding::HalfEven),
+    ("half-up", Rounding::HalfUp),
+    ("toward-zero", Rounding::TowardZero),
+    ("floor", Rounding::Floor),
+    ("ceiling", Rounding::Ceiling),
+];
+
+impl Rounding {
+    pub fn parse(name: &str) -> Option<Rounding> {
+        ROUNDINGS.iter().find(|(n, _)| *n == name).map(|(_, rounding)| *rounding)
+    }
+
+    pub fn name(&self) -> &'static str {
+        ROUNDINGS.iter().find(|(_, r)| r == self).map_or("half-even", |(name, _)| *name)
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        ROUNDINGS.iter().map(|(name, _)| *name).collect()
+    }
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Context {
+    pub precision: u32,
+    pub rounding: Rounding,
+}
+
+// `digits * 10^exponent`, so 1.50 is 150 with exponent -2. Trailing zeros
+// are kept, as they say how exact a number is.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Decimal {
+    digits: BigInt,
+    exponent: i64,
+}
+
+// Reads a literal like `12.5e-3` exactly, with no binary float in between.
+pub fn parse(text: &str) -> Option<Decimal> {
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(i) => (&text[..i], text[i + 1..].parse::<i64>().ok()?),
+        None => (text, 0)
+    };
+    let (whole, fraction) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, "")
+    };
+    let digits = format!("{}{}", whole, fraction).parse::<BigInt>().ok()?;
+    Some(Decimal { digits, exponent: exponent - fraction.len() as i64 })
+}
+
+// Whole numbers and decimals, when at least one of the two is a decimal;
+// the arithmetic in `value` works these out exactly.
+pub fn pair(x: &Value, y: &Value) -> Option<(Decimal, Decimal)> {
+    match (x, y) {
+        (Value::Int(_), Value::Int(_)) => None,
+        _ => Some((of(x)?, of(y)?))
+    }
+}
+
+pub fn of(x: &Value) -> Option<Decimal> {
+    match x {
+        Value::Int(n) => Some(Decimal { digits: BigInt::from(*n), exponent: 0 }),
+        Value::Decimal(x) => Some(x.clone()),
+        _ => None
+    }
+}
+
+// A whole number too large for an integer, kept exactly.
+pub fn whole(n: BigInt) -> Decimal {
+    Decimal { digits: n, exponent: 0 }
+}
+
+// Rounds a result, and every number in a list, to the precision.
+pub fn round(x: Value, context: Context) -> Result<Value, String> {
+    match x {
+        Value::Decimal(x) => x.round(context).map(Value::Decimal),
+        Value::List(items) => items.into_iter().map(|x| round(x, context)).collect::<Result<_, _>>().map(Value::List),
+        x => Ok(x)
+    }
+}
+
+fn power_of_ten(n: i64) -> BigUint {
+    BigUint::from(10u8).pow(n as u32)
+}
+
+fn length(digits: &BigUint) -> i64 {
+    digits.to_string().len() as i64
+}
+
+// Rounds to the precision. `inexact` says something nonzero was already
+// cut off below the last digit, which settles ties and floor or ceiling;
+// callers pass at least one digit more than the precision when it is set.
+fn rounded(digits: BigInt, exponent: i64, inexact: bool, context: Context) -> Result<Decimal, String> {
+    let (sign, magnitude) = (digits.sign(), digits.magnitude().clone());
+    let drop = length(&magnitude) - context.precision as i64;
+    let (digits, exponent) = match drop {
+        drop if drop <= 0 => (digits, exponent),
+        drop => {
+            let scale = power_of_ten(drop);
+            let (mut quotient, remainder) = (&magnitude / &scale, &magnitude % &scale);
+            let half = &scale / 2u8;
+            let cut = remainder.bits() > 0 || inexact;
+            let up = match context.rounding {
+                Rounding::TowardZero => false,
+                Rounding::Floor => sign == Sign::Minus && cut,
+                Rounding::Ceiling => sign == Sign::Plus && cut,
+                Rounding::HalfUp => remainder >= half,
+                Rounding::HalfEven => remainder > half || (remainder == half && (inexact || quotient.bit(0)))
+            };
+            if up {
+                quotient += 1u8;
+            }
+            // Rounding 9.99 up carries into a new digit, and 10.0 is one too many.
+            match length(&quotient) > context.precision as i64 {
+                true => (BigInt::from_biguint(sign, quotient / 10u8), exponent + drop + 1),
+                false => (BigInt::from_biguint(sign, quotient), exponent + drop)
+            }
+        }
+    };
+    let adjusted = exponent + length(digits.magnitude()) - 1;
+    match digits.sign() {
+        Sign::NoSign => Ok(Decimal { digits, exponent: exponent.clamp(-MAX_EXPONENT, MAX_EXPONENT) }),
+        _ if adjusted.abs() > MAX_EXPONENT => Err(String::from("Decimal result out of range")),
+        _ => Ok(Decimal { digits, exponent })
+    }
+}
+
+// Drops trailing zeros from an exact result until it reaches the exponent
+// its operands suggest, so that 1/4 is 0.25 and 1.00/4 is 0.25 too.
+fn reduce(mut digits: BigInt, mut exponent: i64, ideal: i64) -> (BigInt, i64) {
+    if digits.sign() == Sign::NoSign {
+        return (digits, ideal);
+    }
+    let ten = BigInt::from(10u8);
+    while exponent < ideal && (&digits % &ten).sign() == Sign::NoSign {
+        digits /= &ten;
+        exponent += 1;
+    }
+    (digits, exponent)
+}
+
+fn one() -> Decimal {
+    Decimal { digits: BigInt::from(1u8), exponent: 0 }
+}
+
+// 2 atanh(z) with z = (x - 1) / (x + 1), which is ln x, summed until the
+// terms drop below the working precision.
+fn log_series(x: &Decimal, working: Context) -> Result<Decimal, String> {
+    let z = x.sub(&one()).div(&x.add(&one()), working)?;
+    let square = z.mul(&z).round(working)?;
+    let (mut sum, mut power) = (z.clone(), z);
+    for k in (3u32..).step_by(2) {
+        power = power.mul(&square).round(working)?;
+        let term = power.div(&whole(BigInt::from(k)), working)?;
+        if term.is_zero() || term.adjusted() < sum.adjusted() - working.precision as i64 {
+            break;
+        }
+        sum = sum.add(&term).round(working)?;
+    }
+    Ok(sum.add(&sum))
+}
+
+impl Decimal {
+    pub fn add(&self, other: &Decimal) -> Decimal {
+        let exponent = self.exponent.min(other.exponent);
+        let scaled = |x: &Decimal| &x.digits * BigInt::from(power_of_ten(x.exponent - exponent));
+        Decimal { digits: scaled(self) + scaled(other), exponent }
+    }
+
+    pub fn sub(&self, other: &Decimal) -> Decimal {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &Decimal) -> Decimal {
+        Decimal { digits: &self.digits * &other.digits, exponent: self.exponent + other.exponent }
+    }
+
+    pub fn div(&self, other: &Decimal, context: Context) -> Result<Decimal, String> {
+        if other.is_zero() {
+            return Err(String::from("Division by zero"));
+        }
+        let (a, b) = (length(self.digits.magnitude()), length(other.digits.magnitude()));
+        // Enough extra digits for the quotient to go one past the precision.
+        let shift = (context.precision as i64 + 1 + b - a).max(0);
+        let numerator = &self.digits * BigInt::from(power_of_ten(shift));
+        let (quotient, remainder) = (&numerator / &other.digits, &numerator % &other.digits);
+        let exponent = self.exponent - other.exponent - shift;
+        let exact = remainder.sign() == Sign::NoSign;
+        let (digits, exponent) = match exact {
+            true => reduce(quotient, exponent, self.exponent - other.exponent),
+            false => (quotient, exponent)
+        };
+        rounded(digits, exponent, !exact, context)
+    }
+
+    pub fn sqrt(&self, context: Context) -> Result<Decimal, String> {
+        if self.digits.sign() == Sign::Minus {
+            return Err(String::from("Square root of a negative decimal"));
+        }
+        // The radicand gets an even exponent and twice the digits the root needs.
+        let mut shift = (2 * (context.precision as i64 + 1) - length(self.digits.magnitude())).max(0);
+        if (self.exponent - shift) % 2 != 0 {
+            shift += 1;
+        }
+        let radicand = &self.digits * BigInt::from(power_of_ten(shift));
+        let root = radicand.sqrt();
+        let exact = &root * &root == radicand;
+        let exponent = (self.exponent - shift) / 2;
+        let (digits, exponent) = match exact {
+            true => reduce(root, exponent, self.exponent.div_euclid(2)),
+            false => (root, exponent)
+        };
+        rounded(digits, exponent, !exact, context)
+    }
+
+    // Square-and-multiply with guard digits, rounded once at the end.
+    pub fn pow(&self, n: i128, context: Context) -> Result<Decimal, String> {
+        let working = Context { precision: context.precision + 40, rounding: Rounding::HalfEven };
+        let (mut base, mut e) = (self.clone(), n.unsigned_abs());
+        let mut result = Decimal { digits: BigInt::from(1u8), exponent: 0 };
+        while e > 0 {
+            if e & 1 == 1 {
+                result = result.mul(&base).round(working)?;
+            }
+            e >>= 1;
+            if e > 0 {
+                base = base.mul(&base).round(working)?;
+            }
+        }
+        if n < 0 {
+            result = Decimal { digits: BigInt::from(1u8), exponent: 0 }.div(&result, working)?;
+        }
+        result.round(context)
+    }
+
+    // e^x from the Taylor series of x / 2^k, which is below a half, squared
+    // back up k times. The guard digits cover what the squaring loses.
+    pub fn exp(&self, context: Context) -> Result<Decimal, String> {
+        if self.is_zero() {
+            return Ok(one());
+        }
+        // e^23100 is past the largest exponent a decimal may have.
+        if self.to_f64().abs() > 23_100.0 {
+            return Err(String::from("Decimal result out of range"));
+        }
+        let halvings = (2.0 * self.to_f64().abs()).log2().ceil().max(0.0) as usize;
+        let working = Context { precision: context.precision + 25, rounding: Rounding::HalfEven };
+        let r = self.div(&whole(BigInt::from(1u8) << halvings), working)?;
+        let (mut sum, mut term) = (one(), one());
+        for n in 1u32.. {
+            term = term.mul(&r).div(&whole(BigInt::from(n)), working)?;
+            if term.is_zero() || term.adjusted() < sum.adjusted() - working.precision as i64 {
+                break;
+            }
+            sum = sum.add(&term).round(working)?;
+        }
+        for _ in 0..halvings {
+            sum = sum.mul(&sum).round(working)?;
+        }
+        rounded(sum.digits, sum.exponent, true, context)
+    }
+
+    // The natural logarithm. Numbers near 1 go straight to the series; the
+    // rest are f * 2^-j * 10^n first, with f between a half and 1.
+    pub fn ln(&self, context: Context) -> Result<Decimal, String> {
+        if self.digits.sign() != Sign::Plus {
+            return Err(String::from("Logarithm of a decimal that is not positive"));
+        }
+        if self.whole_number() == Some(BigInt::from(1u8)) {
+            return Ok(whole(BigInt::ZERO));
+        }
+        let working = Context { precision: context.precision + 25, rounding: Rounding::HalfEven };
+        let sum = match self.to_f64() {
+            x if (0.5..2.0).contains(&x) => log_series(self, working)?,
+            _ => {
+                let n = self.adjusted() + 1;
+                let two = whole(BigInt::from(2u8));
+                let mut f = Decimal { digits: self.digits.clone(), exponent: self.exponent - n };
+                let mut j = 0u8;
+                while f.to_f64() < 0.5 {
+                    f = f.mul(&two);
+                    j += 1;
+                }
+                let ln2 = log_series(&two, working)?;
+                let ln10 = ln2.mul(&whole(BigInt::from(3u8))).add(&log_series(&parse("1.25").unwrap(), working)?);
+                log_series(&f, working)?.sub(&ln2.mul(&whole(BigInt::from(j)))).add(&ln10.mul(&whole(BigInt::from(n))))
+            }
+        };
+        rounded(sum.digits, sum.exponent, true, context)
+    }
+
+    // x^y for positive x and any y, as e^(y ln x).
+    pub fn power(&self, y: &Decimal, context: Context) -> Result<Decimal, String> {
+        let working = Context { precision: context.precision + 25, rounding: Rounding::HalfEven };
+        self.ln(working)?.mul(y).round(working)?.exp(context)
+    }
+
+    pub fn round(&self, context: Context) -> Result<Decimal, String> {
+        rounded(self.digits.clone(), self.exponent, false, context)
+    }
+
+    pub fn neg(&self) -> Decimal {
+        Decimal { digits: -&self.digits, exponent: self.exponent }
+    }
+
+    pub fn abs(&self) -> Decimal {
+        Decimal { digits: BigInt::from(self.digits.magnitude().clone()), exponent: self.exponent }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.sign() == Sign::NoSign
+    }
+
+    // The power of ten of the leading digit, so 0.0123 gives -2.
+    fn adjusted(&self) -> i64 {
+        self.exponent + length(self.digits.magnitude()) - 1
+    }
+
+    // The whole number this is, if it is one that fits.
+    pub fn integer(&self) -> Option<i128> {
+        match self.exponent {
+            exponent if exponent < 40 => self.whole_number().and_then(|n| i128::try_from(&n).ok()),
+            _ => None
+        }
+    }
+
+    // The whole number this is, however large.
+    pub fn whole_number(&self) -> Option<BigInt> {
+        match reduce(self.digits.clone(), self.exponent, 0) {
+            (digits, exponent) if exponent >= 0 => Some(digits * BigInt::from(power_of_ten(exponent))),
+            _ => None
+        }
+    }
+
+    // The last 128 bits of a whole number in two's complement, which is all
+    // a machine word keeps of it.
+    pub fn low_bits(&self) -> Option<i128> {
+        let modulus = BigInt::from(1u8) << 128;
+        let n = (self.whole_number()? % &modulus + &modulus) % &modulus;
+        u128::try_from(&n).ok().map(|n| n as i128)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    // Rounded half-even to a whole number of 10^exponent.
+    fn quantize(&self, exponent: i64) -> Decimal {
+        let shift = self.exponent - exponent;
+        if shift >= 0 {
+            return Decimal { digits: &self.digits * BigInt::from(power_of_ten(shift)), exponent };
+        }
+        let (sign, magnitude) = (self.digits.sign(), self.digits.magnitude());
+        let scale = power_of_ten(-shift);
+        let (mut quotient, remainder) = (magnitude / &scale, magnitude % &scale);
+        let half = &scale / 2u8;
+        if remainder > half || (remainder == half && quotient.bit(0)) {
+            quotient += 1u8;
+        }
+        Decimal { digits: BigInt::from_biguint(sign, quotient), exponent }
+    }
+
+    // Exactly `places` digits after the point, rounded half-even.
+    pub fn fixed(&self, places: usize) -> String {
+        let x = self.quantize(-(places as i64));
+        let sign = if x.digits.sign() == Sign::Minus { "-" } else { "" };
+        let digits = format!("{:0>1$}", x.digits.magnitude().to_string(), places + 1);
+        match digits.split_at(digits.len() - places) {
+            (whole, "") => format!("{}{}", sign, whole),
+            (whole, fraction) => format!("{}{}.{}", sign, whole, fraction)
+        }
+    }
+
+    // `1.25e-7`, with an exponent that is a multiple of `step`, so a step of
+    // 3 gives engineering notation. Every digit shows unless `places` is set.
+    pub fn scientific(&self, places: Option<usize>, step: i64) -> String {
+        let adjusted = match self.is_zero() {
+            true => 0,
+            false => self.exponent + length(self.digits.magnitude()) - 1
+        };
+        let mantissa = |exponent: i64| {
+            let x = Decimal { digits: self.digits.clone(), exponent: self.exponent - exponent };
+            x.fixed(places.unwrap_or((-x.exponent).max(0) as usize))
+        };
+        let mut exponent = adjusted.div_euclid(step) * step;
+        let mut text = mantissa(exponent);
+        // Rounding can carry the mantissa up to the next power, as 9.99 to 10.0.
+        if text.trim_start_matches('-').split('.').next().is_some_and(|whole| whole.len() as i64 > step) {
+            exponent += step;
+            text = mantissa(exponent);
+        }
+        format!("{}e{}", text, exponent)
+    }
+}
+
+// Plain digits for everyday sizes and `1.5e-9` otherwise, both of which
+// read back in as the same number. Like floats, only magnitudes below 1e-6
+// or from 1e16 up take the scientific form.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.digits.sign() == Sign::Minus { "-" } else { "" };
+        let digits = self.digits.magnitude().to_string();
+        let adjusted = self.exponent + digits.len() as i64 - 1;
+        if self.is_zero() && self.exponent > 0 {
+            write!(f, "0")
+        } else if self.exponent > 0 && adjusted < 16 {
+            write!(f, "{}{}{}", sign, digits, "0".repeat(self.exponent as usize))
+        } else if self.exponent <= 0 && adjusted >= -6 {
+            let point = digits.len() as i64 + self.exponent;
+            match point {
+                _ if self.exponent == 0 => write!(f, "{}{}", sign, digits),
+                point if point > 0 => write!(f, "{}{}.{}", sign, &digits[..point as usize], &digits[point as usize..]),
+                point => write!(f, "{}0.{}{}", sign, "0".repeat(-point as usize), digits)
+            }
+        } else {
+            match &digits[1..] {
+                "" => write!(f, "{}{}e{}", sign, &digits[..1], adjusted),
+                rest => write!(f, "{}{}.{}e{}", sign, &digits[..1], rest, adjusted)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decimal(text: &str) -> Decimal {
+        parse(text).unwrap()
+    }
+
+    fn context(precision: u32, rounding: &str) -> Context {
+        Context { precision, rounding: Rounding::parse(rounding).unwrap() }
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(decimal("0.1").to_string(), "0.1");
+        assert_eq!(decimal("1.50").to_string(), "1.50");
+        assert_eq!(decimal("2.5e-3").to_string(), "0.0025");
+        assert_eq!(decimal("1e3").to_string(), "1000");
+        assert_eq!(decimal("1.5e20").to_string(), "1.5e20");
+        assert_eq!(decimal("1.5e-9").to_string(), "1.5e-9");
+        assert_eq!(decimal("0.1000000000000000000000000000001").to_string(), "0.1000000000000000000000000000001");
+        assert_eq!(parse("1.5x"), None);
+    }
+
+    #[test]
+    fn exact_arithmetic() {
+        assert_eq!(decimal("0.1").add(&decimal("0.2")), decimal("0.3"));
+        assert_eq!(decimal("1.10").add(&decimal("2.20")).to_string(), "3.30");
+        assert_eq!(decimal("1.5").sub(&decimal("2")).to_string(), "-0.5");
+        assert_eq!(decimal("1.05").mul(&decimal("1.05")).to_string(), "1.1025");
+    }
+
+    #[test]
+    fn division() {
+        let fifty = context(50, "half-even");
+        assert_eq!(decimal("1").div(&decimal("3"), fifty).unwrap().to_string(), format!("0.{}", "3".repeat(50)));
+        assert_eq!(decimal("2").div(&decimal("3"), context(5, "half-even")).unwrap().to_string(), "0.66667");
+        assert_eq!(decimal("1").div(&decimal("4"), fifty).unwrap().to_string(), "0.25");
+        assert_eq!(decimal("1.00").div(&decimal("4"), fifty).unwrap().to_string(), "0.25");
+        assert_eq!(decimal("6").div(&decimal("3"), fifty).unwrap().to_string(), "2");
+        assert_eq!(decimal("10").div(&decimal("0.1"), fifty).unwrap().to_string(), "100");
+        assert_eq!(decimal("1").div(&decimal("0"), fifty), Err(String::from("Division by zero")));
+    }
+
+    #[test]
+    fn notations() {
+        assert_eq!(decimal("2.675").fixed(2), "2.68");
+        assert_eq!(decimal("2.665").fixed(2), "2.66");
+        assert_eq!(decimal("-0.001").fixed(2), "0.00");
+        assert_eq!(decimal("1.5").fixed(0), "2");
+        assert_eq!(decimal("12").fixed(3), "12.000");
+        assert_eq!(decimal("0.1000000000000000000000000000001").fixed(31), "0.1000000000000000000000000000001");
+        assert_eq!(decimal("12345.60").scientific(None, 1), "1.234560e4");
+        assert_eq!(decimal("-0.000123").scientific(Some(1), 1), "-1.2e-4");
+        assert_eq!(decimal("9.96").scientific(Some(1), 1), "1.0e1");
+        assert_eq!(decimal("12345").scientific(None, 3), "12.345e3");
+        assert_eq!(decimal("0.00047").scientific(None, 3), "470e-6");
+        assert_eq!(decimal("999.96").scientific(Some(1), 3), "1.0e3");
+        assert_eq!(decimal("0").scientific(None, 1), "0e0");
+    }
+
+    #[test]
+    fn rounding_modes() {
+        let round = |x: &str, rounding: &str| decimal(x).round(context(2, rounding)).unwrap().to_string();
+        assert_eq!(round("2.25", "half-even"), "2.2");
+        assert_eq!(round("2.35", "half-even"), "2.4");
+        assert_eq!(round("2.25", "half-up"), "2.3");
+        assert_eq!(round("-2.25", "half-up"), "-2.3");
+        assert_eq!(round("2.29", "toward-zero"), "2.2");
+        assert_eq!(round("-2.21", "floor"), "-2.3");
+        assert_eq!(round("2.21", "floor"), "2.2");
+        assert_eq!(round("2.21", "ceiling"), "2.3");
+        assert_eq!(round("9.99", "half-up"), "10");
+        // A tie only in the kept digits is broken by what was cut off before.
+        assert_eq!(decimal("5").div(&decimal("3"), context(1, "half-even")).unwrap().to_string(), "2");
+        assert_eq!(decimal("1").div(&decimal("-3"), context(2, "floor")).unwrap().to_string(), "-0.34");
+    }
+
+    #[test]
+    fn roots_and_powers() {
+        let fifty = context(50, "half-even");
+        assert_eq!(decimal("2").sqrt(fifty).unwrap().to_string(), "1.4142135623730950488016887242096980785696718753769");
+        assert_eq!(decimal("2.25").sqrt(fifty).unwrap().to_string(), "1.5");
+        assert_eq!(decimal("4").sqrt(fifty).unwrap().to_string(), "2");
+        assert!(decimal("-4").sqrt(fifty).is_err());
+        assert_eq!(decimal("1.05").pow(10, fifty).unwrap().to_string(), "1.62889462677744140625");
+        assert_eq!(decimal("2").pow(-2, fifty).unwrap().to_string(), "0.25");
+        assert_eq!(decimal("1e9000").pow(2, fifty), Err(String::from("Decimal result out of range")));
+    }
+
+    #[test]
+    fn exponentials_and_logarithms() {
+        let fifty = context(50, "half-even");
+        assert_eq!(decimal("1").exp(fifty).unwrap().to_string(), "2.7182818284590452353602874713526624977572470937000");
+        assert_eq!(decimal("-30").exp(fifty).unwrap().to_string(), "9.3576229688401746049158322233787067449583226889359e-14");
+        assert_eq!(decimal("1000").exp(fifty).unwrap().to_string(), "1.9700711140170469938888793522433231253169379853238e434");
+        assert_eq!(decimal("0").exp(fifty).unwrap().to_string(), "1");
+        assert_eq!(decimal("1e6").exp(fifty), Err(String::from("Decimal result out of range")));
+        assert_eq!(decimal("2").ln(fifty).unwrap().to_string(), "0.69314718055994530941723212145817656807550013436026");
+        assert_eq!(decimal("1e-30").ln(fifty).unwrap().to_string(), "-69.077552789821370520539743640530926228033044658863");
+        assert_eq!(decimal("1.0000000001").ln(fifty).unwrap().to_string(), "9.9999999995000000000333333333308333333335333333333e-11");
+        assert_eq!(decimal("12345.678").ln(fifty).unwrap().to_string(), "9.4210613212918319765267839910714460489418336300805");
+        assert_eq!(decimal("1.000").ln(fifty).unwrap().to_string(), "0");
+        assert!(decimal("-1").ln(fifty).is_err());
+        assert_eq!(decimal("2").power(&decimal("0.5"), fifty).unwrap().to_string(), "1.4142135623730950488016887242096980785696718753769");
+        assert_eq!(decimal("10").power(&decimal("1.5"), context(30, "half-even")).unwrap().to_string(), "31.6227766016837933199889354443");
+    }
+}
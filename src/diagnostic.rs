@@ -0,0 +1,57 @@
+use super::lexer::Span;
+
+const RED: &str = "\x1b[1;31m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(PartialEq, Debug)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn new(message: String, span: Option<Span>) -> Diagnostic {
+        Diagnostic { message, span }
+    }
+
+    pub fn render(&self, line: &str, color: bool) -> String {
+        let (start, end) = if color { (RED, RESET) } else { ("", "") };
+        match self.span {
+            Some(span) => {
+                let width = std::cmp::max(1, span.end.saturating_sub(span.start));
+                format!("{}\n{}{}^{} {}{}",
+                    line,
+                    " ".repeat(span.start),
+                    start,
+                    "~".repeat(width - 1),
+                    self.message,
+                    end
+                )
+            },
+            None => format!("{}{}{}", start, self.message, end)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_span() {
+        let diagnostic = Diagnostic::new(String::from("Undefined variable: rate"), Some(Span { start: 4, end: 8 }));
+        assert_eq!(diagnostic.render("2 * rate", false), "2 * rate\n    ^~~~ Undefined variable: rate");
+    }
+
+    #[test]
+    fn render_end_of_input() {
+        let diagnostic = Diagnostic::new(String::from("Missing right parenthesis"), Some(Span { start: 4, end: 4 }));
+        assert_eq!(diagnostic.render("(1 +", false), "(1 +\n    ^ Missing right parenthesis");
+    }
+
+    #[test]
+    fn render_color() {
+        let diagnostic = Diagnostic::new(String::from("Division by zero"), None);
+        assert_eq!(diagnostic.render("1 / 0", true), "\x1b[1;31mDivision by zero\x1b[0m");
+    }
+}
@@ -1,66 +1,504 @@
 use super::parser::ParseTree;
 use super::parser::SumOp;
 use super::parser::ProdOp;
+use super::parser::BitOp;
+use super::value::{self, Value, Width};
+use super::units;
+use super::functions;
+use super::matrix;
+use super::stats;
+use super::symbolic;
+use super::solver;
+use super::calculus;
+use super::constants;
+use super::integers;
+use super::decimal::{self, Context, Rounding};
 use std::collections::HashMap;
 
-pub fn evaluate<'a>(parsed: Result<Box<ParseTree>, String>, symbols: HashMap<String, i32>) -> (Result<i32, String>, HashMap<String, i32>) {
+#[derive(PartialEq, Debug, Default, Copy, Clone)]
+pub struct Settings {
+    pub width: Option<Width>,
+    pub symbolic: bool,
+    // Decimal mode: the significant digits every result is rounded to.
+    pub precision: Option<u32>,
+    pub rounding: Rounding,
+}
+
+impl Settings {
+    // In programmer mode every intermediate result is truncated to the
+    // chosen width, the way the machine would do it.
+    // Decimal mode rounds them to the precision instead.
+    fn wrap(&self, value: Result<Value, String>) -> Result<Value, String> {
+        match (self.width, self.context(), value) {
+            (Some(width), _, Ok(Value::Int(x))) => Ok(Value::Int(width.wrap(x))),
+            (Some(width), _, Ok(Value::Decimal(x))) if x.low_bits().is_some() => Ok(Value::Int(width.wrap(x.low_bits().unwrap()))),
+            (Some(width), _, Ok(_)) => Err(format!("Only integers are allowed in {} mode", width.name())),
+            (None, Some(context), Ok(x)) => decimal::round(x, context),
+            (_, _, value) => value
+        }
+    }
+
+    fn context(&self) -> Option<Context> {
+        self.precision.map(|precision| Context { precision, rounding: self.rounding })
+    }
+
+    // Literals with a fraction are exact decimals in decimal mode and
+    // floats otherwise. Integers past i128 come here too.
+    fn literal(&self, text: &str) -> Result<Value, String> {
+        match (self.precision, decimal::parse(text)) {
+            (Some(_), Some(x)) => Ok(Value::Decimal(x)),
+            // Whole numbers too large for an integer stay exact.
+            (None, Some(x)) if text.chars().all(|c| c.is_ascii_digit()) => Ok(Value::Decimal(x)),
+            _ => text.parse().map(Value::Real).map_err(|_| format!("Not a number: {}", text))
+        }
+    }
+
+    // Decimal mode divides whole numbers and decimals to the precision, so
+    // 1/3 is 0.333... rather than 0. Whole quotients stay whole numbers.
+    fn divide(&self, x: Value, y: Value) -> Result<Value, String> {
+        match (self.context(), decimal::of(&x), decimal::of(&y)) {
+            (Some(context), Some(a), Some(b)) => {
+                let quotient = a.div(&b, context)?;
+                match (x, y, quotient.integer()) {
+                    (Value::Int(_), Value::Int(_), Some(n)) => Ok(Value::Int(n)),
+                    _ => Ok(Value::Decimal(quotient))
+                }
+            },
+            _ => x.div(y)
+        }
+    }
+
+    // Whole powers of decimals, negative powers of whole numbers, and
+    // fractional powers of positive numbers.
+    fn power(&self, x: Value, y: Value) -> Result<Value, String> {
+        let n = decimal::of(&y).and_then(|n| n.integer());
+        match (self.context(), decimal::of(&x), n, decimal::of(&y)) {
+            (Some(context), Some(a), Some(n), _) if matches!(x, Value::Decimal(_)) || n < 0 => a.pow(n, context).map(Value::Decimal),
+            (Some(context), Some(a), None, Some(b)) if x.to_f64() > 0.0 => a.power(&b, context).map(Value::Decimal),
+            _ => x.pow(y)
+        }
+    }
+
+    // Square roots, exponentials, logarithms and averages of decimals and
+    // whole numbers are worked out to the precision. Anything else that
+    // would turn exact arguments into a float is an error, rather than a
+    // quiet loss of the digits decimal mode promises.
+    fn call(&self, name: &str, arguments: Vec<Value>) -> Result<Value, String> {
+        let divide = |x, n| self.divide(x, n);
+        match (self.context(), name, arguments.as_slice()) {
+            (Some(_), "sqrt" | "exp" | "ln", [Value::List(items)]) => {
+                items.iter().map(|x| self.call(name, vec![x.clone()])).collect::<Result<_, _>>().map(Value::List)
+            },
+            (Some(context), "sqrt", [x]) if x.to_f64() >= 0.0 && decimal::of(x).is_some() => decimal::of(x).unwrap().sqrt(context).map(Value::Decimal),
+            (Some(context), "exp", [x]) if decimal::of(x).is_some() => decimal::of(x).unwrap().exp(context).map(Value::Decimal),
+            (Some(context), "ln", [x]) if x.to_f64() > 0.0 && decimal::of(x).is_some() => decimal::of(x).unwrap().ln(context).map(Value::Decimal),
+            (Some(_), "mean", _) => stats::mean_by(arguments, &divide),
+            (Some(_), "median", _) => stats::median_by(arguments, &divide),
+            (Some(_), "variance", _) => stats::variance_by(arguments, &divide),
+            (Some(_), "stdev", _) => self.call("sqrt", vec![stats::variance_by(arguments, &divide)?]),
+            (Some(_), _, _) if !arguments.iter().any(inexact) => match functions::call(name, arguments)? {
+                x if inexact(&x) => Err(format!("{} has no exact decimal result; use :precision off for floating point", name)),
+                x => Ok(x)
+            },
+            _ => functions::call(name, arguments)
+        }
+    }
+}
+
+fn inexact(x: &Value) -> bool {
+    match x {
+        Value::Real(_) => true,
+        Value::List(items) => items.iter().any(inexact),
+        _ => false
+    }
+}
+
+pub fn evaluate(parsed: Result<Box<ParseTree>, String>, symbols: HashMap<String, Value>, settings: &Settings) -> (Result<Value, String>, HashMap<String, Value>) {
     match parsed {
         Err(e) => (Err(e), symbols),
-        Ok(parse_tree) => evaluate_tree(*parse_tree, symbols)
+        // Assignments make their own formulas.
+        Ok(parse_tree) if settings.symbolic && !matches!(*parse_tree, ParseTree::Assign(..)) => formula(*parse_tree, symbols, settings),
+        Ok(parse_tree) => evaluate_tree(*parse_tree, symbols, settings)
     }
 
 }
 
-fn evaluate_tree<'a>(parse_tree: ParseTree, symbols: HashMap<String, i32>) -> (Result<i32, String>, HashMap<String, i32>) {
+fn evaluate_tree(parse_tree: ParseTree, symbols: HashMap<String, Value>, settings: &Settings) -> (Result<Value, String>, HashMap<String, Value>) {
     match parse_tree {
         ParseTree::Sum(op, left, right) => {
-            let (x, symbols) = evaluate_tree(*left, symbols);
+            let (x, symbols) = evaluate_tree(*left, symbols, settings);
             if x.is_err() { return (x, symbols) };
-            let (y, symbols) = evaluate_tree(*right, symbols);
+            // `a + p%` adds p percent of a, so 200 + 15% is 230.
+            let (y, symbols) = match *right {
+                ParseTree::Percent(p) => {
+                    let (p, symbols) = evaluate_tree(*p, symbols, settings);
+                    let a = x.clone().unwrap();
+                    (p.and_then(|p| percent(a.mul(p)?, settings)), symbols)
+                },
+                right => evaluate_tree(right, symbols, settings)
+            };
             if y.is_err() { return (y, symbols) };
             match op {
-                SumOp::Plus => (Ok(x.unwrap() + y.unwrap()), symbols),
-                SumOp::Minus => (Ok(x.unwrap() - y.unwrap()), symbols)
+                SumOp::Plus => (settings.wrap(x.unwrap().add(y.unwrap())), symbols),
+                SumOp::Minus => (settings.wrap(x.unwrap().sub(y.unwrap())), symbols)
             }
         },
+        // Percentages are taken last, so that `15% of 60` and `7 as % of 20`
+        // stay whole numbers when they can.
+        ParseTree::Prod(ProdOp::Times, left, right) if matches!(*left, ParseTree::Percent(_)) || matches!(*right, ParseTree::Percent(_)) => {
+            let (left, a) = hundredths(*left);
+            let (right, b) = hundredths(*right);
+            let (x, symbols) = evaluate_tree(left, symbols, settings);
+            if x.is_err() { return (x, symbols) };
+            let (y, symbols) = evaluate_tree(right, symbols, settings);
+            if y.is_err() { return (y, symbols) };
+            let product = x.unwrap().mul(y.unwrap()).and_then(|product| (0..a + b).try_fold(product, |x, _| percent(x, settings)));
+            (settings.wrap(product), symbols)
+        },
+        ParseTree::Prod(ProdOp::Divide, left, right) if matches!(*right, ParseTree::Percent(_)) => {
+            let (right, _) = hundredths(*right);
+            let (x, symbols) = evaluate_tree(*left, symbols, settings);
+            if x.is_err() { return (x, symbols) };
+            let (y, symbols) = evaluate_tree(right, symbols, settings);
+            if y.is_err() { return (y, symbols) };
+            (settings.wrap(x.unwrap().mul(Value::Int(100)).and_then(|x| fraction(x, y.unwrap(), settings))), symbols)
+        },
         ParseTree::Prod(op, left, right) => {
-            let (x, symbols) = evaluate_tree(*left, symbols);
+            let (x, symbols) = evaluate_tree(*left, symbols, settings);
             if x.is_err() { return (x, symbols) };
-            let (y, symbols) = evaluate_tree(*right, symbols);
+            let (y, symbols) = evaluate_tree(*right, symbols, settings);
             if y.is_err() { return (y, symbols) };
-            match op {
-                ProdOp::Times => (Ok(x.unwrap() * y.unwrap()), symbols),
-                ProdOp::Divide => (Ok(x.unwrap() / y.unwrap()), symbols)
+            match (op, settings.width, x.unwrap(), y.unwrap()) {
+                // Two 64-bit operands can overflow even i128, but only the low bits survive anyway.
+                (ProdOp::Times, Some(_), Value::Int(x), Value::Int(y)) => (settings.wrap(Ok(Value::Int(x.wrapping_mul(y)))), symbols),
+                (ProdOp::Times, _, x, y) => (settings.wrap(x.mul(y)), symbols),
+                (ProdOp::Divide, _, x, y) => (settings.wrap(settings.divide(x, y)), symbols),
+                (ProdOp::MatMul, _, x, y) => (settings.wrap(matrix::matmul(x, y)), symbols)
+            }
+        },
+        ParseTree::Bit(op, left, right) => {
+            let (x, symbols) = evaluate_tree(*left, symbols, settings);
+            if x.is_err() { return (x, symbols) };
+            let (y, symbols) = evaluate_tree(*right, symbols, settings);
+            if y.is_err() { return (y, symbols) };
+            let (x, y) = (x.unwrap(), y.unwrap());
+            let result = match (op, settings.width) {
+                (BitOp::And, _) => x.and(y),
+                (BitOp::Or, _) => x.or(y),
+                (BitOp::Xor, _) => x.xor(y),
+                (BitOp::ShiftLeft, Some(width)) => value::shift(&y, width.bits).and_then(|n| Ok(Value::Int(x.integer()? << n))),
+                (BitOp::ShiftLeft, None) => x.shift_left(y),
+                (BitOp::ShiftRight, Some(width)) => value::shift(&y, width.bits).and_then(|n| Ok(Value::Int(x.integer()? >> n))),
+                (BitOp::ShiftRight, None) => x.shift_right(y)
+            };
+            (settings.wrap(result), symbols)
+        },
+        ParseTree::Power(left, right) => {
+            let (x, symbols) = evaluate_tree(*left, symbols, settings);
+            if x.is_err() { return (x, symbols) };
+            let (y, symbols) = evaluate_tree(*right, symbols, settings);
+            if y.is_err() { return (y, symbols) };
+            match (settings.width, x.unwrap(), y.unwrap()) {
+                (Some(_), Value::Int(x), Value::Int(n)) if n >= 0 => (settings.wrap(Ok(Value::Int(wrapping_pow(x, n)))), symbols),
+                (_, x, y) => (settings.wrap(settings.power(x, y)), symbols)
             }
         },
         ParseTree::Unary(op, tree) => {
-            let (x, symbols) = evaluate_tree(*tree, symbols);
+            let (x, symbols) = evaluate_tree(*tree, symbols, settings);
             if x.is_err() { return (x, symbols) };
             match op {
-                SumOp::Plus => (Ok(x.unwrap()), symbols),
-                SumOp::Minus => (Ok(-x.unwrap()), symbols)
+                SumOp::Plus => (x, symbols),
+                SumOp::Minus => (settings.wrap(x.unwrap().neg()), symbols)
             }
         },
-        ParseTree::Num(x) => (Ok(x), symbols),
-        ParseTree::Assign(s, tree) => {
-            let (eval, mut symbols) = evaluate_tree(*tree, symbols);
+        ParseTree::Not(tree) => {
+            let (x, symbols) = evaluate_tree(*tree, symbols, settings);
+            if x.is_err() { return (x, symbols) };
+            (settings.wrap(x.unwrap().not()), symbols)
+        },
+        ParseTree::Percent(tree) => {
+            let (x, symbols) = evaluate_tree(*tree, symbols, settings);
+            (settings.wrap(x.and_then(|x| percent(x, settings))), symbols)
+        },
+        ParseTree::Factorial(tree) => {
+            let (x, symbols) = evaluate_tree(*tree, symbols, settings);
+            (settings.wrap(x.and_then(integers::factorial)), symbols)
+        },
+        ParseTree::Num(x) => (settings.wrap(Ok(Value::Int(x))), symbols),
+        ParseTree::Real(x) => (settings.wrap(Ok(Value::Real(x))), symbols),
+        // Literals are taken exactly as written; only results are rounded.
+        ParseTree::Decimal(text) if settings.width.is_none() => (settings.literal(&text), symbols),
+        ParseTree::Decimal(text) => (settings.wrap(settings.literal(&text)), symbols),
+        ParseTree::Imag(x) => (settings.wrap(value::complex(0.0, x)), symbols),
+        // Derivatives and simplified formulas are numbers once every
+        // variable in them has a value, and stay formulas until then.
+        // Solutions, integrals and series are always numbers, and
+        // `solve(..., bind)` also assigns its solutions.
+        ParseTree::Call(name, mut arguments) if functions::FORMS.contains(&name.as_str()) || calculus::is_series(&name, &arguments) => {
+            if let Some(first) = arguments.first_mut() {
+                *first = resolve(first.clone(), &symbols);
+            }
+            let named = symbolic::differentiated(&ParseTree::Call(name.clone(), arguments.clone()));
+            let formula = match (name.as_str(), arguments.len()) {
+                ("solve", _) => {
+                    let mut symbols = symbols;
+                    let x = solver::solve(arguments, &mut symbols, settings);
+                    return (settings.wrap(x), symbols);
+                },
+                ("integrate", _) => return (settings.wrap(calculus::integrate(arguments, &symbols, settings)), symbols),
+                ("sum", _) | ("product", _) => return (settings.wrap(calculus::series(&name, arguments, &symbols, settings)), symbols),
+                ("diff", _) => symbolic::nested(&arguments),
+                (_, 1) => Ok(symbolic::simplify(arguments.remove(0))),
+                (_, count) => Err(format!("{} takes 1 argument, got {}", name, count))
+            };
+            match formula {
+                Err(e) => (Err(e), symbols),
+                Ok(tree) if has_free(&tree, &named, &symbols) => {
+                    (settings.wrap(Ok(Value::Formula(Box::new(tree)))), symbols)
+                },
+                Ok(tree) => evaluate_tree(tree, symbols, settings)
+            }
+        },
+        ParseTree::Call(name, arguments) => {
+            let mut values = Vec::new();
+            let mut symbols = symbols;
+            for argument in arguments {
+                let (x, rest) = evaluate_tree(argument, symbols, settings);
+                symbols = rest;
+                match x {
+                    Ok(x) => values.push(x),
+                    Err(e) => return (Err(e), symbols)
+                }
+            }
+            (settings.wrap(settings.call(&name, values)), symbols)
+        },
+        ParseTree::List(items) => {
+            let mut values = Vec::new();
+            let mut symbols = symbols;
+            for item in items {
+                let (x, rest) = evaluate_tree(item, symbols, settings);
+                symbols = rest;
+                match x {
+                    Ok(x) => values.push(x),
+                    Err(e) => return (Err(e), symbols)
+                }
+            }
+            (settings.wrap(Ok(Value::List(values))), symbols)
+        },
+        ParseTree::Index(list, index) => {
+            let (x, symbols) = evaluate_tree(*list, symbols, settings);
+            if x.is_err() { return (x, symbols) };
+            let (i, symbols) = evaluate_tree(*index, symbols, settings);
+            if i.is_err() { return (i, symbols) };
+            (matrix::index(x.unwrap(), i.unwrap()), symbols)
+        },
+        ParseTree::Range(start, end) => {
+            let (a, symbols) = evaluate_tree(*start, symbols, settings);
+            if a.is_err() { return (a, symbols) };
+            let (b, symbols) = evaluate_tree(*end, symbols, settings);
+            if b.is_err() { return (b, symbols) };
+            (settings.wrap(stats::sequence(a.unwrap(), b.unwrap(), Value::Int(1))), symbols)
+        },
+        ParseTree::Equation(..) => (Err(String::from("Equations can only be solved, as in solve(x^2 = 2, x)")), symbols),
+        // Only checks that the units agree; the session shows the answer
+        // counted in the target unit.
+        ParseTree::Convert(tree, target) => {
+            let description = describe(&target);
+            let amount = scaled(&target);
+            let (x, symbols) = evaluate_tree(*tree, symbols, settings);
+            if x.is_err() { return (x, symbols) };
+            let (y, symbols) = evaluate_tree(*target, symbols, settings);
+            match y {
+                Err(e) => (Err(e), symbols),
+                Ok(Value::Quantity(..)) if amount => (Err(String::from("Convert to units alone, as in `to m` rather than `to 5 m`")), symbols),
+                Ok(target @ Value::Quantity(..)) => (x.clone().and_then(|x| x.convert(&target)).and(x), symbols),
+                Ok(_) => (Err(format!("Cannot convert to {}", description)), symbols)
+            }
+        },
+        ParseTree::Assign(s, _) | ParseTree::Define(s, _) | ParseTree::Const(s, _) if constants::lookup(&s).is_some() => {
+            (Err(constants::read_only(&s)), symbols)
+        },
+        ParseTree::Assign(s, tree) | ParseTree::Define(s, tree) | ParseTree::Const(s, tree) => {
+            let (eval, mut symbols) = match settings.symbolic {
+                true => formula(*tree, symbols, settings),
+                false => evaluate_tree(*tree, symbols, settings)
+            };
             if eval.is_err() { return (eval, symbols) };
             let x = eval.unwrap();
-            symbols.insert(s, x);
+            // The old value goes first, since formulas that use it will
+            // use the new one.
+            let old = symbols.remove(&s);
+            if let Value::Formula(tree) = &x {
+                if symbolic::depends(&resolve((**tree).clone(), &symbols), &s) {
+                    if let Some(old) = old {
+                        symbols.insert(s.clone(), old);
+                    }
+                    return (Err(format!("{} cannot be defined in terms of itself", s)), symbols);
+                }
+            }
+            symbols.insert(s, x.clone());
             (Ok(x), symbols)
         },
         ParseTree::Var(s) => {
             match symbols.get(&s) {
-                Some(x) => (Ok(*x), symbols),
-                None => (Err(format!("Undefined variable: {}", s)), symbols)
+                Some(Value::Formula(tree)) => {
+                    let tree = resolve((**tree).clone(), &symbols);
+                    formula(tree, symbols, settings)
+                },
+                Some(x) => (settings.wrap(Ok(x.clone())), symbols),
+                None if s.starts_with('$') => (Err(format!("No output {}", s)), symbols),
+                // Built-in names only fill in for names the user has not taken.
+                None if s == "i" => (settings.wrap(Ok(Value::Complex(0.0, 1.0))), symbols),
+                None if constants::lookup(&s).is_some() => (settings.wrap(Ok(constants::lookup(&s).unwrap())), symbols),
+                None if units::lookup(&s).is_some() => {
+                    let (size, unit) = units::lookup(&s).unwrap();
+                    (settings.wrap(Ok(Value::Quantity(size, unit))), symbols)
+                },
+                None => {
+                    let error = match suggest(&s, &symbols) {
+                        Some(name) => format!("Undefined variable: {} (did you mean `{}`?)", s, name),
+                        None => format!("Undefined variable: {}", s)
+                    };
+                    (Err(error), symbols)
+                }
             }
         }
     }
 }
 
+// A formula is worked out whenever it is used, so it follows the values of
+// its variables. Until they all have one it stays a formula.
+fn formula(tree: ParseTree, symbols: HashMap<String, Value>, settings: &Settings) -> (Result<Value, String>, HashMap<String, Value>) {
+    let (x, symbols) = evaluate_tree(tree.clone(), symbols, settings);
+    let free = || {
+        let tree = resolve(tree.clone(), &symbols);
+        has_free(&tree, &symbolic::differentiated(&tree), &symbols)
+    };
+    match x {
+        Err(_) if free() => (settings.wrap(Ok(Value::Formula(Box::new(tree.clone())))), symbols),
+        x => (x, symbols)
+    }
+}
+
+// Writes out every variable that holds a formula. Assignments never let a
+// formula mention itself, so this comes to an end.
+pub fn resolve(tree: ParseTree, symbols: &HashMap<String, Value>) -> ParseTree {
+    symbolic::substitute(tree, &|name: &str| match symbols.get(name) {
+        Some(Value::Formula(tree)) => Some(resolve((**tree).clone(), symbols)),
+        _ => None
+    })
+}
+
+// Whether some variable in the tree has no value. A variable a derivative
+// is taken in stays free until the user gives it one, even when it is also
+// a unit like `t`.
+fn has_free(tree: &ParseTree, named: &[String], symbols: &HashMap<String, Value>) -> bool {
+    symbolic::variables(tree).iter().any(|name| !is_defined(name, symbols) || (named.contains(name) && !symbols.contains_key(name)))
+}
+
+pub fn is_defined(name: &str, symbols: &HashMap<String, Value>) -> bool {
+    symbols.contains_key(name) || name == "i" || constants::lookup(name).is_some() || units::lookup(name).is_some()
+}
+
+// Takes one `%` off a tree, counting whether there was one.
+fn hundredths(tree: ParseTree) -> (ParseTree, usize) {
+    match tree {
+        ParseTree::Percent(tree) => (*tree, 1),
+        tree => (tree, 0)
+    }
+}
+
+fn percent(x: Value, settings: &Settings) -> Result<Value, String> {
+    fraction(x, Value::Int(100), settings)
+}
+
+// Whole numbers divide exactly when they can and into a real otherwise,
+// rather than truncating like `/` does.
+fn fraction(x: Value, y: Value, settings: &Settings) -> Result<Value, String> {
+    match (x, y) {
+        (x, y) if settings.precision.is_some() => settings.divide(x, y),
+        (Value::Int(a), Value::Int(b)) if b != 0 && a % b == 0 => Ok(Value::Int(a / b)),
+        (Value::Int(a), y @ Value::Int(_)) => Value::Real(a as f64).div(y),
+        (x, y) => x.div(y)
+    }
+}
+
+// Square-and-multiply modulo 2^128, which is all a fixed width keeps.
+fn wrapping_pow(mut x: i128, mut n: i128) -> i128 {
+    let mut result: i128 = 1;
+    while n > 0 {
+        if n & 1 == 1 {
+            result = result.wrapping_mul(x);
+        }
+        x = x.wrapping_mul(x);
+        n >>= 1;
+    }
+    result
+}
+
+fn describe(tree: &ParseTree) -> String {
+    match tree {
+        ParseTree::Var(s) => s.clone(),
+        _ => String::from("an expression")
+    }
+}
+
+// Whether a conversion target has a number in front of its units, like
+// `5 m`. Exponents, as in `m^2`, and the 1 of `1/s` are part of the unit.
+fn scaled(tree: &ParseTree) -> bool {
+    match tree {
+        ParseTree::Prod(_, left, right) => scaled(left) || scaled(right),
+        ParseTree::Power(base, _) => scaled(base),
+        ParseTree::Num(n) => *n != 1,
+        ParseTree::Real(_) | ParseTree::Decimal(_) => true,
+        _ => false
+    }
+}
+
+fn suggest<'a>(name: &str, symbols: &'a HashMap<String, Value>) -> Option<&'a String> {
+    let limit = std::cmp::max(1, name.chars().count() / 3);
+    symbols.keys()
+        .filter(|candidate| !candidate.starts_with('$'))
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit && *distance < name.chars().count())
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + if x == *y { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
 mod test {
     #[allow(unused_imports)]
     use super::*;
+    use crate::lexer::Tokens;
+    use crate::parser::Parser;
+
+    #[test]
+    fn hours() {
+        let hour = Value::Quantity(3600.0, crate::units::lookup("h").unwrap().1);
+        let two = |left: ParseTree, right: ParseTree| {
+            let tree = ParseTree::Prod(ProdOp::Times, Box::new(left), Box::new(right));
+            evaluate_tree(tree, HashMap::new(), &Settings::default()).0
+        };
+        let h = || ParseTree::Var(String::from("h"));
+        assert_eq!(two(ParseTree::Num(2), h()), hour.clone().mul(Value::Int(2)));
+        assert_eq!(two(h(), ParseTree::Num(2)), hour.mul(Value::Int(2)));
+        let (planck, _) = evaluate_tree(ParseTree::Var(String::from("h_P")), HashMap::new(), &Settings::default());
+        assert_eq!(planck, Ok(constants::lookup("h_P").unwrap()));
+    }
 
     #[test]
     fn valid_sum() {
@@ -70,8 +508,8 @@ mod test {
             Box::new(ParseTree::Num(2))
         ));
         let symbols = HashMap::new();
-        let (result, _) = evaluate_tree(*valid_tree, symbols);
-        assert_eq!(result.unwrap(), 3);
+        let (result, _) = evaluate_tree(*valid_tree, symbols, &Settings::default());
+        assert_eq!(result.unwrap(), Value::Int(3));
     }
 
     #[test]
@@ -82,8 +520,8 @@ mod test {
             Box::new(ParseTree::Num(2))
         ));
         let symbols = HashMap::new();
-        let (result, _) = evaluate_tree(*valid_tree, symbols);
-        assert_eq!(result.unwrap(), 2);
+        let (result, _) = evaluate_tree(*valid_tree, symbols, &Settings::default());
+        assert_eq!(result.unwrap(), Value::Int(2));
     }
 
     #[test]
@@ -98,8 +536,8 @@ mod test {
             ))
         ));
         let symbols = HashMap::new();
-        let (result, _) = evaluate_tree(*valid_tree, symbols);
-        assert_eq!(result.unwrap(), -3);
+        let (result, _) = evaluate_tree(*valid_tree, symbols, &Settings::default());
+        assert_eq!(result.unwrap(), Value::Int(-3));
     }
 
     #[test]
@@ -113,8 +551,8 @@ mod test {
             Box::new(ParseTree::Num(1))
         ));
         let symbols = HashMap::new();
-        let (result, _) = evaluate_tree(*valid_tree, symbols);
-        assert_eq!(result.unwrap(), -2); 
+        let (result, _) = evaluate_tree(*valid_tree, symbols, &Settings::default());
+        assert_eq!(result.unwrap(), Value::Int(-2)); 
     }
 
     #[test]
@@ -128,9 +566,9 @@ mod test {
             ))
         ));
         let symbols = HashMap::new();
-        let (result, new_symbols) = evaluate_tree(*valid_tree, symbols);
+        let (result, new_symbols) = evaluate_tree(*valid_tree, symbols, &Settings::default());
         let new_valid_tree = Box::new(ParseTree::Var(String::from("x")));
-        let (new_result, _) = evaluate_tree(*new_valid_tree, new_symbols);
+        let (new_result, _) = evaluate_tree(*new_valid_tree, new_symbols, &Settings::default());
         assert_eq!(new_result.unwrap(), result.unwrap()); 
     }
 
@@ -142,7 +580,206 @@ mod test {
             Box::new(ParseTree::Var(String::from("x")))
         ));
         let symbols = HashMap::new();
-        let (result, _) = evaluate_tree(*valid_tree, symbols);
+        let (result, _) = evaluate_tree(*valid_tree, symbols, &Settings::default());
         assert!(result.is_err(), "Undefined variable: x"); 
     }
+
+    #[test]
+    fn suggest_variable() {
+        let mut symbols = HashMap::new();
+        symbols.insert(String::from("rate"), Value::Int(7));
+        symbols.insert(String::from("total"), Value::Int(1));
+        let (result, symbols) = evaluate_tree(ParseTree::Var(String::from("rte")), symbols, &Settings::default());
+        assert_eq!(result, Err(String::from("Undefined variable: rte (did you mean `rate`?)")));
+        let (result, _) = evaluate_tree(ParseTree::Var(String::from("z")), symbols, &Settings::default());
+        assert_eq!(result, Err(String::from("Undefined variable: z")));
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let invalid_tree = Box::new(ParseTree::Prod(
+            ProdOp::Divide,
+            Box::new(ParseTree::Num(1)),
+            Box::new(ParseTree::Num(0))
+        ));
+        let symbols = HashMap::new();
+        let (result, _) = evaluate_tree(*invalid_tree, symbols, &Settings::default());
+        assert_eq!(result, Err(String::from("Division by zero")));
+    }
+
+    #[test]
+    fn programmer_mode() {
+        let byte = Settings { width: Width::parse("u8"), ..Settings::default() };
+        let overflow_tree = Box::new(ParseTree::Sum(
+            SumOp::Plus,
+            Box::new(ParseTree::Num(250)),
+            Box::new(ParseTree::Num(10))
+        ));
+        let (result, symbols) = evaluate_tree(*overflow_tree, HashMap::new(), &byte);
+        assert_eq!(result, Ok(Value::Int(4)));
+        let not_tree = Box::new(ParseTree::Not(Box::new(ParseTree::Num(0))));
+        let (result, symbols) = evaluate_tree(*not_tree, symbols, &byte);
+        assert_eq!(result, Ok(Value::Int(255)));
+        let shift_tree = Box::new(ParseTree::Bit(
+            BitOp::ShiftLeft,
+            Box::new(ParseTree::Num(1)),
+            Box::new(ParseTree::Num(8))
+        ));
+        let (result, symbols) = evaluate_tree(*shift_tree, symbols, &byte);
+        assert!(result.is_err());
+        let (result, _) = evaluate_tree(ParseTree::Real(1.5), symbols, &byte);
+        assert_eq!(result, Err(String::from("Only integers are allowed in u8 mode")));
+    }
+
+    #[test]
+    fn wide_multiplication() {
+        let word = Settings { width: Width::parse("u64"), ..Settings::default() };
+        let square_tree = Box::new(ParseTree::Prod(
+            ProdOp::Times,
+            Box::new(ParseTree::Num(u64::MAX as i128)),
+            Box::new(ParseTree::Num(u64::MAX as i128))
+        ));
+        let (result, _) = evaluate_tree(*square_tree, HashMap::new(), &word);
+        assert_eq!(result, Ok(Value::Int(1)));
+    }
+
+    #[test]
+    fn bitwise_ops() {
+        let mixed_tree = Box::new(ParseTree::Bit(
+            BitOp::Or,
+            Box::new(ParseTree::Bit(
+                BitOp::ShiftLeft,
+                Box::new(ParseTree::Num(1)),
+                Box::new(ParseTree::Num(4))
+            )),
+            Box::new(ParseTree::Bit(
+                BitOp::Xor,
+                Box::new(ParseTree::Num(6)),
+                Box::new(ParseTree::Num(3))
+            ))
+        ));
+        let (result, _) = evaluate_tree(*mixed_tree, HashMap::new(), &Settings::default());
+        assert_eq!(result, Ok(Value::Int(21)));
+    }
+
+    #[test]
+    fn complex_numbers() {
+        // 3+4i
+        let literal_tree = Box::new(ParseTree::Sum(
+            SumOp::Plus,
+            Box::new(ParseTree::Num(3)),
+            Box::new(ParseTree::Imag(4.0))
+        ));
+        let (result, symbols) = evaluate_tree(*literal_tree, HashMap::new(), &Settings::default());
+        assert_eq!(result, Ok(Value::Complex(3.0, 4.0)));
+        let call_tree = ParseTree::Call(String::from("sqrt"), vec![ParseTree::Num(-1)]);
+        let (result, symbols) = evaluate_tree(call_tree, symbols, &Settings::default());
+        assert_eq!(result, Ok(Value::Complex(0.0, 1.0)));
+        let (result, _) = evaluate_tree(ParseTree::Var(String::from("i")), symbols, &Settings::default());
+        assert_eq!(result, Ok(Value::Complex(0.0, 1.0)));
+    }
+
+    #[test]
+    fn matrix_values() {
+        let row = |a, b| ParseTree::List(vec![ParseTree::Num(a), ParseTree::Num(b)]);
+        // m = [[1, 2], [3, 4]]
+        let assign_tree = ParseTree::Assign(String::from("m"), Box::new(ParseTree::List(vec![row(1, 2), row(3, 4)])));
+        let (_, symbols) = evaluate_tree(assign_tree, HashMap::new(), &Settings::default());
+        // m[0][1] + m[1][0]
+        let index = |i, j| Box::new(ParseTree::Index(
+            Box::new(ParseTree::Index(Box::new(ParseTree::Var(String::from("m"))), Box::new(ParseTree::Num(i)))),
+            Box::new(ParseTree::Num(j))
+        ));
+        let sum_tree = ParseTree::Sum(SumOp::Plus, index(0, 1), index(1, 0));
+        let (result, symbols) = evaluate_tree(sum_tree, symbols, &Settings::default());
+        assert_eq!(result, Ok(Value::Int(5)));
+        let product_tree = ParseTree::Prod(ProdOp::MatMul, Box::new(ParseTree::Var(String::from("m"))), Box::new(row(1, 0)));
+        let (result, _) = evaluate_tree(product_tree, symbols, &Settings::default());
+        assert_eq!(result, Ok(Value::List(vec![Value::Int(1), Value::Int(3)])));
+    }
+
+    #[test]
+    fn factorials() {
+        let factorial = |n| Box::new(ParseTree::Factorial(Box::new(ParseTree::Num(n))));
+        // 5! / 3! and -3!
+        let (result, _) = evaluate_tree(ParseTree::Prod(ProdOp::Divide, factorial(5), factorial(3)), HashMap::new(), &Settings::default());
+        assert_eq!(result, Ok(Value::Int(20)));
+        let (result, _) = evaluate_tree(ParseTree::Unary(SumOp::Minus, factorial(3)), HashMap::new(), &Settings::default());
+        assert_eq!(result, Ok(Value::Int(-6)));
+        // 40! - 1, and a literal just as large
+        let big = |tree| evaluate_tree(ParseTree::Sum(SumOp::Minus, tree, Box::new(ParseTree::Num(1))), HashMap::new(), &Settings::default()).0.map(|x| x.to_string());
+        assert_eq!(big(factorial(40)), Ok(String::from("815915283247897734345611269596115894271999999999")));
+        assert_eq!(big(Box::new(ParseTree::Decimal(String::from("123456789012345678901234567890123456789012")))), Ok(String::from("123456789012345678901234567890123456789011")));
+    }
+
+    fn line(line: &str, settings: &Settings) -> Result<String, String> {
+        let mut tokenizer = Tokens::new(line.chars());
+        tokenizer.tokenize();
+        let mut parser = Parser::new();
+        parser.parse(tokenizer.tokens);
+        evaluate(parser.tree, HashMap::new(), settings).0.map(|x| x.to_string())
+    }
+
+    fn decimal(text: &str, precision: u32, rounding: &str) -> Result<String, String> {
+        line(text, &Settings { precision: Some(precision), rounding: Rounding::parse(rounding).unwrap(), ..Settings::default() })
+    }
+
+    #[test]
+    fn percentages() {
+        let float = |text| line(text, &Settings::default());
+        assert_eq!(float("200 + 15%"), Ok(String::from("230")));
+        assert_eq!(float("80 - 20%"), Ok(String::from("64")));
+        assert_eq!(float("15% of 60"), Ok(String::from("9")));
+        assert_eq!(float("7 as % of 20"), Ok(String::from("35")));
+        assert_eq!(float("1 as % of 8"), Ok(String::from("12.5")));
+        assert_eq!(float("50%"), Ok(String::from("0.5")));
+        assert_eq!(float("200 + 10% * 2"), Ok(String::from("200.2")));
+        assert_eq!(float("2 * 200 + 10%"), Ok(String::from("440")));
+        assert_eq!(float("50% * 50%"), Ok(String::from("0.25")));
+        assert_eq!(float("200 m - 25%"), Ok(String::from("150.0 m")));
+    }
+
+    #[test]
+    fn decimal_mode() {
+        let fifty = |line| decimal(line, 50, "half-even");
+        assert_eq!(fifty("0.1 + 0.2"), Ok(String::from("0.3")));
+        assert_eq!(fifty("0.1 + 0.2 - 0.3"), Ok(String::from("0.0")));
+        assert_eq!(fifty("1/3"), Ok(format!("0.{}", "3".repeat(50))));
+        assert_eq!(fifty("6/3"), Ok(String::from("2")));
+        assert_eq!(fifty("19.99 * 3"), Ok(String::from("59.97")));
+        assert_eq!(fifty("200.00 + 15%"), Ok(String::from("230.00")));
+        assert_eq!(fifty("1000 * 1.05^3"), Ok(String::from("1157.625000")));
+        assert_eq!(fifty("sqrt(2)"), Ok(String::from("1.4142135623730950488016887242096980785696718753769")));
+        assert_eq!(fifty("sqrt(-4)"), Ok(String::from("0.0+2.0i")));
+        assert_eq!(decimal("123456789012345678901234567890123456789012 + 1", 60, "half-even"), Ok(String::from("123456789012345678901234567890123456789013")));
+    }
+
+    #[test]
+    fn decimal_rounding() {
+        assert_eq!(decimal("2.345 + 0", 3, "half-even"), Ok(String::from("2.34")));
+        assert_eq!(decimal("2.345 + 0", 3, "half-up"), Ok(String::from("2.35")));
+        assert_eq!(decimal("-2.349 * 1", 3, "ceiling"), Ok(String::from("-2.34")));
+        assert_eq!(decimal("-2.341 * 1", 3, "floor"), Ok(String::from("-2.35")));
+        assert_eq!(decimal("2/3", 3, "toward-zero"), Ok(String::from("0.666")));
+    }
+
+    #[test]
+    fn decimal_functions() {
+        let twenty = |line| decimal(line, 20, "half-even");
+        assert_eq!(twenty("exp(1)"), Ok(String::from("2.7182818284590452354")));
+        assert_eq!(twenty("ln(10)"), Ok(String::from("2.3025850929940456840")));
+        assert_eq!(twenty("2^0.5"), Ok(String::from("1.4142135623730950488")));
+        assert_eq!(twenty("mean(1, 2, 4)"), Ok(String::from("2.3333333333333333333")));
+        assert_eq!(twenty("mean(2, 4)"), Ok(String::from("3")));
+        assert_eq!(twenty("median([1, 2])"), Ok(String::from("1.5")));
+        assert_eq!(twenty("stdev(1, 2, 4)"), Ok(String::from("1.5275252316519466689")));
+        assert_eq!(twenty("arg(1)"), Err(String::from("arg has no exact decimal result; use :precision off for floating point")));
+    }
+
+    #[test]
+    fn distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("rate", "rate"), 0);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,346 @@
+use super::decimal::Decimal;
+use super::value::{Value, Width};
+use num_bigint::Sign;
+
+const NOTATIONS: &[(&str, Notation)] = &[
+    ("dec", Notation::Decimal),
+    ("hex", Notation::Hex),
+    ("bin", Notation::Binary),
+    ("oct", Notation::Octal),
+    ("fixed", Notation::Fixed),
+    ("sci", Notation::Scientific),
+    ("eng", Notation::Engineering),
+    ("polar", Notation::Polar),
+];
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Notation {
+    Decimal,
+    Hex,
+    Binary,
+    Octal,
+    Fixed,
+    Scientific,
+    Engineering,
+    Polar,
+}
+
+impl Notation {
+    pub fn name(&self) -> &'static str {
+        NOTATIONS.iter().find(|(_, n)| n == self).map_or("dec", |(name, _)| *name)
+    }
+
+    // Other bases only write integers, including whole decimals like 40!.
+    pub fn shows(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Notation::Hex | Notation::Binary | Notation::Octal, Value::List(items)) => items.iter().all(|x| self.shows(x)),
+            (Notation::Hex | Notation::Binary | Notation::Octal, Value::Int(_)) => true,
+            (Notation::Hex | Notation::Binary | Notation::Octal, Value::Decimal(x)) => x.whole_number().is_some(),
+            (Notation::Hex | Notation::Binary | Notation::Octal, _) => false,
+            _ => true
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Format {
+    pub notation: Notation,
+    pub precision: Option<usize>,
+    pub grouping: Option<char>,
+}
+
+impl Format {
+    pub fn new() -> Format {
+        Format {
+            notation: Notation::Decimal,
+            precision: None,
+            grouping: None,
+        }
+    }
+
+    pub fn notation(name: &str) -> Option<Notation> {
+        NOTATIONS.iter().find(|(n, _)| *n == name).map(|(_, notation)| *notation)
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        NOTATIONS.iter().map(|(name, _)| *name).collect()
+    }
+
+    pub fn with(self, notation: Option<Notation>) -> Format {
+        match notation {
+            Some(notation) => Format { notation, ..self },
+            None => self
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let name = self.notation.name();
+        let precision = match self.precision {
+            Some(p) => format!(", precision {}", p),
+            None => String::new()
+        };
+        let grouping = match self.grouping {
+            Some(c) => format!(", grouping '{}'", c),
+            None => String::new()
+        };
+        format!("Format: {}{}{}", name, precision, grouping)
+    }
+
+    pub fn show(&self, value: Value) -> String {
+        match (self.notation, value) {
+            (_, Value::List(items)) => {
+                let items: Vec<String> = items.into_iter().map(|x| self.show(x)).collect();
+                format!("[{}]", items.join(", "))
+            },
+            (_, Value::Quantity(x, unit)) => format!("{} {}", self.show(Value::Real(x)), unit),
+            (_, Value::Formula(tree)) => tree.to_string(),
+            (Notation::Polar, Value::Complex(re, im)) => {
+                format!("{} ∠ {}", self.show(Value::Real(re.hypot(im))), self.show(Value::Real(im.atan2(re))))
+            },
+            (_, Value::Complex(re, im)) => self.rectangular(re, im),
+            (Notation::Decimal, Value::Int(x)) => self.integer(x, 10, "", 3),
+            (Notation::Hex, Value::Int(x)) => self.integer(x, 16, "0x", 4),
+            (Notation::Binary, Value::Int(x)) => self.integer(x, 2, "0b", 4),
+            (Notation::Octal, Value::Int(x)) => self.integer(x, 8, "0o", 3),
+            (Notation::Hex, Value::Decimal(x)) if x.whole_number().is_some() => self.whole(&x, 16, "0x", 4),
+            (Notation::Binary, Value::Decimal(x)) if x.whole_number().is_some() => self.whole(&x, 2, "0b", 4),
+            (Notation::Octal, Value::Decimal(x)) if x.whole_number().is_some() => self.whole(&x, 8, "0o", 3),
+            (Notation::Fixed, Value::Decimal(x)) => self.exact(&x.fixed(self.precision.unwrap_or(6))),
+            (Notation::Scientific, Value::Decimal(x)) => x.scientific(self.precision, 1),
+            (Notation::Engineering, Value::Decimal(x)) => x.scientific(self.precision, 3),
+            (Notation::Fixed, value) => self.fixed(value.to_f64(), Some(self.precision.unwrap_or(6))),
+            (Notation::Scientific, value) => scientific(value.to_f64(), self.precision),
+            (Notation::Engineering, value) => engineering(value.to_f64(), self.precision),
+            (Notation::Polar, Value::Int(x)) => self.integer(x, 10, "", 3),
+            // Other bases only make sense for integers, so reals stay decimal.
+            (_, Value::Real(x)) => match self.precision {
+                Some(_) => self.fixed(x, self.precision),
+                None if x != 0.0 && (x.abs() < 1e-6 || x.abs() >= 1e16) => scientific(x, None),
+                None => self.fixed(x, None)
+            },
+            (_, Value::Decimal(x)) => match self.precision {
+                Some(places) => self.exact(&x.fixed(places)),
+                None => self.exact(&x.to_string())
+            }
+        }
+    }
+
+    // Decimals show every digit they have, grouped like reals.
+    fn exact(&self, text: &str) -> String {
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", text)
+        };
+        let end = digits.find(['.', 'e']).unwrap_or(digits.len());
+        format!("{}{}{}", sign, self.group(&digits[..end], 3), &digits[end..])
+    }
+
+    fn rectangular(&self, re: f64, im: f64) -> String {
+        let imaginary = match self.show(Value::Real(im.abs())) {
+            one if one == "1" => String::from("i"),
+            size => format!("{}i", size)
+        };
+        match (re, im < 0.0) {
+            (0.0, negative) => format!("{}{}", if negative { "-" } else { "" }, imaginary),
+            (re, negative) => format!("{} {} {}", self.show(Value::Real(re)), if negative { '-' } else { '+' }, imaginary)
+        }
+    }
+
+    // Programmer mode shows the raw bits of the word, padded to its full
+    // width, so negative numbers appear in two's complement.
+    pub fn word(&self, value: Value, width: Width) -> String {
+        let (x, radix, prefix, size) = match (self.notation, &value) {
+            (Notation::Hex, Value::Int(x)) => (*x, 16u32, "0x", 4),
+            (Notation::Binary, Value::Int(x)) => (*x, 2, "0b", 4),
+            (Notation::Octal, Value::Int(x)) => (*x, 8, "0o", 3),
+            _ => return self.show(value)
+        };
+        let bits = x as u128 & width.mask();
+        let length = (width.bits as usize).div_ceil(radix.trailing_zeros() as usize);
+        let digits = format!("{:0>1$}", to_radix(bits, radix), length);
+        format!("{}{}", prefix, self.group(&digits, size))
+    }
+
+    fn integer(&self, x: i128, radix: u32, prefix: &str, size: usize) -> String {
+        let sign = if x < 0 { "-" } else { "" };
+        let digits = to_radix(x.unsigned_abs(), radix);
+        format!("{}{}{}", sign, prefix, self.group(&digits, size))
+    }
+
+    // Whole numbers past i128, like 40!, keep every digit in other bases too.
+    fn whole(&self, x: &Decimal, radix: u32, prefix: &str, size: usize) -> String {
+        let n = x.whole_number().unwrap();
+        let sign = if n.sign() == Sign::Minus { "-" } else { "" };
+        format!("{}{}{}", sign, prefix, self.group(&n.magnitude().to_str_radix(radix), size))
+    }
+
+    fn fixed(&self, x: f64, precision: Option<usize>) -> String {
+        let text = match precision {
+            Some(p) => format!("{:.*}", p, x.abs()),
+            None => format!("{}", significant(x.abs()))
+        };
+        let (whole, fraction) = match text.find('.') {
+            Some(i) => text.split_at(i),
+            None => (text.as_str(), "")
+        };
+        let sign = if x < 0.0 && text.chars().any(|c| c != '0' && c != '.') { "-" } else { "" };
+        format!("{}{}{}", sign, self.group(whole, 3), fraction)
+    }
+
+    fn group(&self, digits: &str, size: usize) -> String {
+        match self.grouping {
+            None => digits.to_string(),
+            Some(separator) => {
+                let mut grouped = String::new();
+                for (i, c) in digits.chars().enumerate() {
+                    if i > 0 && (digits.len() - i).is_multiple_of(size) {
+                        grouped.push(separator);
+                    }
+                    grouped.push(c);
+                }
+                grouped
+            }
+        }
+    }
+}
+
+fn to_radix(mut x: u128, radix: u32) -> String {
+    let mut digits = Vec::new();
+    loop {
+        digits.push(std::char::from_digit((x % radix as u128) as u32, radix).unwrap());
+        x /= radix as u128;
+        if x == 0 {
+            break;
+        }
+    }
+    digits.iter().rev().collect()
+}
+
+fn scientific(x: f64, precision: Option<usize>) -> String {
+    match precision {
+        Some(p) => format!("{:.*e}", p, x),
+        None => format!("{:e}", significant(x))
+    }
+}
+
+// Without a set precision, the last couple of digits are mostly rounding
+// noise, as in `0.1 + 0.2`, so only the first 15 are shown.
+fn significant(x: f64) -> f64 {
+    format!("{:.14e}", x).parse().unwrap_or(x)
+}
+
+fn engineering(x: f64, precision: Option<usize>) -> String {
+    let mantissa = |m: f64| match precision {
+        Some(p) => format!("{:.*}", p, m),
+        None => format!("{}", significant(m))
+    };
+    if x == 0.0 {
+        return format!("{}e0", mantissa(0.0));
+    }
+    let mut exponent = (x.abs().log10().floor() as i32).div_euclid(3) * 3;
+    let mut text = mantissa(x / 10f64.powi(exponent));
+    // Rounding can carry the mantissa up to 1000, which belongs to the next group.
+    if text.trim_start_matches('-').split('.').next().is_some_and(|whole| whole.len() > 3) {
+        exponent += 3;
+        text = mantissa(x / 10f64.powi(exponent));
+    }
+    format!("{}e{}", text, exponent)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::decimal;
+
+    #[test]
+    fn integer_bases() {
+        let format = Format::new();
+        assert_eq!(format.show(Value::Int(255)), "255");
+        assert_eq!(format.with(Some(Notation::Hex)).show(Value::Int(255)), "0xff");
+        assert_eq!(format.with(Some(Notation::Binary)).show(Value::Int(10)), "0b1010");
+        assert_eq!(format.with(Some(Notation::Octal)).show(Value::Int(-493)), "-0o755");
+        let forty = Value::Decimal(decimal::parse("815915283247897734345611269596115894272000000000").unwrap());
+        assert_eq!(format.with(Some(Notation::Hex)).show(forty.clone()), "0x8eeae81b84c7f27e080fde64ff05254000000000");
+        assert_eq!(format.with(Some(Notation::Hex)).show(Value::Decimal(decimal::parse("-340282366920938463463374607431768211456").unwrap())), "-0x100000000000000000000000000000000");
+        assert_eq!(format.show(forty), "815915283247897734345611269596115894272000000000");
+    }
+
+    #[test]
+    fn digit_grouping() {
+        let format = Format { grouping: Some('_'), ..Format::new() };
+        assert_eq!(format.show(Value::Int(1000000)), "1_000_000");
+        assert_eq!(format.show(Value::Int(-100)), "-100");
+        assert_eq!(format.with(Some(Notation::Binary)).show(Value::Int(255)), "0b1111_1111");
+        let format = Format { grouping: Some(','), ..Format::new() };
+        assert_eq!(format.show(Value::Real(1234567.25)), "1,234,567.25");
+    }
+
+    #[test]
+    fn machine_words() {
+        let byte = Width::parse("i8").unwrap();
+        let format = Format::new().with(Some(Notation::Hex));
+        assert_eq!(format.word(Value::Int(-1), byte), "0xff");
+        assert_eq!(format.word(Value::Int(10), Width::parse("u16").unwrap()), "0x000a");
+        let format = Format { grouping: Some('_'), ..format.with(Some(Notation::Binary)) };
+        assert_eq!(format.word(Value::Int(5), byte), "0b0000_0101");
+        assert_eq!(Format::new().word(Value::Int(-1), byte), "-1");
+    }
+
+    #[test]
+    fn complex_notations() {
+        let format = Format::new();
+        assert_eq!(format.show(Value::Complex(3.0, 4.0)), "3 + 4i");
+        assert_eq!(format.show(Value::Complex(0.5, -1.0)), "0.5 - i");
+        assert_eq!(format.show(Value::Complex(0.0, -2.5)), "-2.5i");
+        let format = Format { precision: Some(3), ..format.with(Some(Notation::Polar)) };
+        assert_eq!(format.show(Value::Complex(0.0, 2.0)), "2.000 ∠ 1.571");
+        assert_eq!(format.show(Value::Int(7)), "7");
+    }
+
+    #[test]
+    fn real_notations() {
+        let format = Format::new();
+        assert_eq!(format.show(Value::Real(0.5)), "0.5");
+        assert_eq!(format.show(Value::Real(1e20)), "1e20");
+        assert_eq!(format.show(Value::Real(0.1 + 0.2)), "0.3");
+        assert_eq!(format.with(Some(Notation::Fixed)).show(Value::Real(2.0)), "2.000000");
+        assert_eq!(format.with(Some(Notation::Scientific)).show(Value::Int(12345)), "1.2345e4");
+        let format = Format { precision: Some(2), ..Format::new() };
+        assert_eq!(format.show(Value::Real(1.23456)), "1.23");
+        assert_eq!(format.with(Some(Notation::Scientific)).show(Value::Real(-0.000123)), "-1.23e-4");
+        assert_eq!(format.with(Some(Notation::Hex)).show(Value::Real(2.5)), "2.50");
+    }
+
+    #[test]
+    fn base_values() {
+        assert!(Notation::Hex.shows(&Value::List(vec![Value::Int(1), Value::Decimal(decimal::parse("1e40").unwrap())])));
+        assert!(!Notation::Binary.shows(&Value::Real(255.5)));
+        assert!(!Notation::Octal.shows(&Value::Decimal(decimal::parse("2.5").unwrap())));
+        assert!(Notation::Scientific.shows(&Value::Real(255.5)));
+        assert_eq!(Notation::Hex.name(), "hex");
+    }
+
+    #[test]
+    fn engineering_notation() {
+        let format = Format::new().with(Some(Notation::Engineering));
+        assert_eq!(format.show(Value::Int(12345)), "12.345e3");
+        assert_eq!(format.show(Value::Real(0.00047)), "470e-6");
+        assert_eq!(format.show(Value::Real(0.00012)), "120e-6");
+        assert_eq!(format.show(Value::Int(0)), "0e0");
+        let format = Format { precision: Some(1), ..format };
+        assert_eq!(format.show(Value::Real(999.96)), "1.0e3");
+    }
+
+    #[test]
+    fn decimal_notations() {
+        let third = Value::Decimal(crate::decimal::parse(&format!("0.{}", "3".repeat(30))).unwrap());
+        let big = Value::Decimal(crate::decimal::parse("123456789012345678901234567890.5").unwrap());
+        let format = Format { precision: Some(25), ..Format::new() };
+        assert_eq!(format.show(third.clone()), format!("0.{}", "3".repeat(25)));
+        assert_eq!(format.with(Some(Notation::Scientific)).show(third.clone()), format!("3.{}e-1", "3".repeat(25)));
+        assert_eq!(format.with(Some(Notation::Engineering)).show(third), format!("333.{}e-3", "3".repeat(25)));
+        let format = Format { grouping: Some(','), ..Format::new() };
+        assert_eq!(format.with(Some(Notation::Fixed)).show(big.clone()), "123,456,789,012,345,678,901,234,567,890.500000");
+        assert_eq!(format.with(Some(Notation::Scientific)).show(big), "1.234567890123456789012345678905e29");
+    }
+}
@@ -0,0 +1,107 @@
+use super::matrix;
+use super::stats;
+use super::integers;
+use super::value::Value;
+
+type Function = fn(Value) -> Result<Value, String>;
+type Variadic = fn(Vec<Value>) -> Result<Value, String>;
+
+// Built-in functions of one number, called as `name(x)`. Given a list they
+// apply to every element.
+const FUNCTIONS: &[(&str, Function)] = &[
+    ("sqrt", Value::sqrt),
+    ("abs", Value::abs),
+    ("arg", Value::arg),
+    ("conj", Value::conj),
+    ("re", Value::re),
+    ("im", Value::im),
+    ("exp", Value::exp),
+    ("ln", Value::ln),
+    ("log", Value::log),
+    ("sin", Value::sin),
+    ("cos", Value::cos),
+    ("tan", Value::tan),
+    ("isprime", integers::isprime),
+    ("nextprime", integers::nextprime),
+    ("totient", integers::totient),
+    ("factor", integers::factor),
+    ("factorial", integers::factorial),
+];
+
+// Functions that take a whole vector or matrix.
+const MATRIX_FUNCTIONS: &[(&str, Function)] = &[
+    ("transpose", matrix::transpose),
+    ("det", matrix::det),
+    ("inverse", matrix::inverse),
+];
+
+// Functions that check their own arguments, mostly statistics over a list.
+const LIST_FUNCTIONS: &[(&str, Variadic)] = &[
+    ("sum", stats::sum),
+    ("count", stats::count),
+    ("mean", stats::mean),
+    ("median", stats::median),
+    ("mode", stats::mode),
+    ("variance", stats::variance),
+    ("stdev", stats::stdev),
+    ("min", stats::min),
+    ("max", stats::max),
+    ("percentile", stats::percentile),
+    ("range", stats::range),
+    ("nCr", integers::ncr),
+    ("nPr", integers::npr),
+    ("gcd", integers::gcd),
+    ("lcm", integers::lcm),
+    ("modpow", integers::modpow),
+];
+
+// Functions that take formulas rather than values as arguments; the
+// evaluator deals with these before evaluating anything.
+pub const FORMS: &[&str] = &["diff", "simplify", "solve", "integrate", "product"];
+
+pub fn names() -> Vec<&'static str> {
+    let lists = LIST_FUNCTIONS.iter().map(|(name, _)| *name);
+    FUNCTIONS.iter().chain(MATRIX_FUNCTIONS).map(|(name, _)| *name).chain(lists).chain(FORMS.iter().copied()).collect()
+}
+
+pub fn call(name: &str, arguments: Vec<Value>) -> Result<Value, String> {
+    if let Some((_, function)) = LIST_FUNCTIONS.iter().find(|(n, _)| *n == name) {
+        return function(arguments);
+    }
+    let find = |table: &[(&str, Function)]| table.iter().find(|(n, _)| *n == name).map(|(_, function)| *function);
+    let (function, whole) = match (find(FUNCTIONS), find(MATRIX_FUNCTIONS)) {
+        (Some(function), _) => (function, false),
+        (_, Some(function)) => (function, true),
+        _ => return Err(format!("Unknown function: {}", name))
+    };
+    let count = arguments.len();
+    match arguments.into_iter().next() {
+        Some(x) if count == 1 && whole => function(x),
+        Some(x) if count == 1 => each(function, x),
+        _ => Err(format!("{} takes 1 argument, got {}", name, count))
+    }
+}
+
+fn each(function: Function, x: Value) -> Result<Value, String> {
+    match x {
+        Value::List(items) => items.into_iter().map(|x| each(function, x)).collect::<Result<_, _>>().map(Value::List),
+        x => function(x)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn call_functions() {
+        assert_eq!(call("abs", vec![Value::Int(-3)]), Ok(Value::Int(3)));
+        assert_eq!(call("sqrt", vec![Value::Int(-4)]), Ok(Value::Complex(0.0, 2.0)));
+        assert_eq!(call("frobnicate", Vec::new()), Err(String::from("Unknown function: frobnicate")));
+        assert_eq!(call("re", Vec::new()), Err(String::from("re takes 1 argument, got 0")));
+        let list = Value::List(vec![Value::Int(-1), Value::Int(2)]);
+        assert_eq!(call("abs", vec![list.clone()]), Ok(Value::List(vec![Value::Int(1), Value::Int(2)])));
+        assert_eq!(call("max", vec![list.clone()]), Ok(Value::Int(2)));
+        assert_eq!(call("det", vec![list]), Err(String::from("Expected a matrix")));
+    }
+}
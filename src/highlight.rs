@@ -0,0 +1,97 @@
+use super::lexer::{Token, Tokens};
+use std::collections::HashSet;
+
+const NUMBER: &str = "\x1b[33m";
+const DEFINED: &str = "\x1b[32m";
+const UNDEFINED: &str = "\x1b[90m";
+const OPERATOR: &str = "\x1b[35m";
+const ERROR: &str = "\x1b[1;31m";
+const RESET: &str = "\x1b[0m";
+
+pub fn highlight(line: &str, defined: &HashSet<String>) -> String {
+    if line.trim_start().starts_with(':') {
+        return line.to_string();
+    }
+    let mut tokenizer = Tokens::new(line.chars());
+    tokenizer.tokenize_partial();
+    let unmatched = unmatched_parens(&tokenizer.tokens);
+    let chars: Vec<char> = line.chars().collect();
+    let mut highlighted = String::new();
+    let mut position = 0;
+    for (i, (token, span)) in tokenizer.tokens.iter().zip(tokenizer.spans.iter()).enumerate() {
+        highlighted.extend(&chars[position..span.start]);
+        let text: String = chars[span.start..span.end].iter().collect();
+        let color = match token {
+            Token::Num(_) | Token::Decimal(_) | Token::Imag(_) => Some(NUMBER),
+            Token::Ident(name) if name == "to" => Some(OPERATOR),
+            Token::Ident(name) if defined.contains(name) => Some(DEFINED),
+            Token::Ident(_) => Some(UNDEFINED),
+            Token::Op(_) | Token::Assign | Token::Define => Some(OPERATOR),
+            Token::LParen | Token::RParen | Token::LBracket | Token::RBracket if unmatched.contains(&i) => Some(ERROR),
+            Token::Error(_) | Token::Overflow(_) => Some(ERROR),
+            _ => None
+        };
+        match color {
+            Some(color) => highlighted.push_str(&format!("{}{}{}", color, text, RESET)),
+            None => highlighted.push_str(&text)
+        }
+        position = span.end;
+    }
+    highlighted.extend(&chars[position..]);
+    highlighted
+}
+
+fn unmatched_parens(tokens: &[Token]) -> Vec<usize> {
+    let mut unmatched = Vec::new();
+    for (left, right) in &[(Token::LParen, Token::RParen), (Token::LBracket, Token::RBracket)] {
+        let mut open = Vec::new();
+        for (i, token) in tokens.iter().enumerate() {
+            if token == left {
+                open.push(i);
+            } else if token == right && open.pop().is_none() {
+                unmatched.push(i);
+            }
+        }
+        unmatched.extend(open);
+    }
+    unmatched
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn highlight_tokens() {
+        let mut defined = HashSet::new();
+        defined.insert(String::from("x"));
+        assert_eq!(
+            highlight("x = y+12", &defined),
+            format!("{d}x{r} {o}={r} {u}y{r}{o}+{r}{n}12{r}", d = DEFINED, u = UNDEFINED, o = OPERATOR, n = NUMBER, r = RESET)
+        );
+    }
+
+    #[test]
+    fn highlight_parens() {
+        let defined = HashSet::new();
+        assert_eq!(
+            highlight("(1))", &defined),
+            format!("({n}1{r}){e}){r}", n = NUMBER, e = ERROR, r = RESET)
+        );
+        assert_eq!(
+            highlight("((1)", &defined),
+            format!("{e}({r}({n}1{r})", n = NUMBER, e = ERROR, r = RESET)
+        );
+        assert_eq!(
+            highlight("[(1]", &defined),
+            format!("[{e}({r}{n}1{r}]", n = NUMBER, e = ERROR, r = RESET)
+        );
+    }
+
+    #[test]
+    fn highlight_errors() {
+        let defined = HashSet::new();
+        assert_eq!(highlight("2# # 3", &defined), format!("{e}2#{r} {e}#{r} {n}3{r}", n = NUMBER, e = ERROR, r = RESET));
+        assert_eq!(highlight(":save x", &defined), ":save x");
+    }
+}
@@ -0,0 +1,198 @@
+use super::highlight::highlight;
+use super::session::{Session, COMMANDS};
+use super::units;
+use super::constants;
+use super::functions;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::{CmdKind, Highlighter};
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io::{self, BufRead, IsTerminal};
+use std::path::PathBuf;
+
+const PROMPT: &str = ">> ";
+const CONTINUATION: &str = "...> ";
+
+pub struct Helper {
+    names: Vec<String>,
+    defined: HashSet<String>,
+    color: bool,
+}
+
+impl Completer for Helper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(complete(line, pos, &self.names))
+    }
+}
+
+impl Hinter for Helper {
+    type Hint = String;
+}
+
+impl Highlighter for Helper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        if self.color {
+            Cow::Owned(highlight(line, &self.defined))
+        } else {
+            Cow::Borrowed(line)
+        }
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, _kind: CmdKind) -> bool {
+        self.color
+    }
+}
+
+impl Validator for Helper {}
+
+impl rustyline::Helper for Helper {}
+
+fn complete(line: &str, pos: usize, names: &[String]) -> (usize, Vec<String>) {
+    let start = line[..pos]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphabetic() || *c == '_')
+        .last()
+        .map_or(pos, |(i, _)| i);
+    let word = &line[start..pos];
+    let mut candidates: Vec<String> = if line[..start].trim_start() == ":" {
+        COMMANDS.iter().filter(|c| c.starts_with(word)).map(|c| c.to_string()).collect()
+    } else if word.is_empty() {
+        Vec::new()
+    } else {
+        names.iter().filter(|n| n.starts_with(word)).cloned().collect()
+    };
+    candidates.sort();
+    candidates.dedup();
+    (start, candidates)
+}
+
+enum Input {
+    Line(String),
+    Interrupted,
+    End,
+}
+
+pub enum Reader {
+    Editor(Box<Editor<Helper, FileHistory>>, Option<PathBuf>),
+    Plain(io::Lines<io::StdinLock<'static>>),
+}
+
+impl Reader {
+    pub fn new() -> Reader {
+        if io::stdin().is_terminal() {
+            if let Ok(mut editor) = Editor::new() {
+                editor.set_helper(Some(Helper {
+                    names: Vec::new(),
+                    defined: HashSet::new(),
+                    color: color(),
+                }));
+                let history = history_path();
+                if let Some(path) = &history {
+                    // A missing history file just means this is the first session.
+                    let _ = editor.load_history(path);
+                }
+                return Reader::Editor(Box::new(editor), history);
+            }
+        }
+        Reader::Plain(io::stdin().lock().lines())
+    }
+
+    pub fn read_line(&mut self, session: &Session) -> Option<String> {
+        if let Reader::Editor(editor, _) = self {
+            if let Some(helper) = editor.helper_mut() {
+                helper.names = session.names();
+                helper.names.extend(functions::names().into_iter().map(String::from));
+                helper.names.extend(constants::names().into_iter().map(String::from));
+                helper.defined = session.symbols.keys().cloned().collect();
+                helper.defined.extend(units::names().into_iter().map(String::from));
+                helper.defined.extend(constants::names().into_iter().map(String::from));
+                helper.defined.extend(functions::names().into_iter().map(String::from));
+                helper.defined.insert(String::from("i"));
+            }
+        }
+        'entry: loop {
+            let mut text = match self.read_raw(PROMPT) {
+                Input::Line(line) => line,
+                Input::Interrupted => continue,
+                Input::End => return None
+            };
+            while session.is_incomplete(&text) {
+                match self.read_raw(CONTINUATION) {
+                    Input::Line(line) => {
+                        text.push(' ');
+                        text.push_str(line.trim());
+                    },
+                    Input::Interrupted => continue 'entry,
+                    Input::End => break
+                }
+            }
+            if let Reader::Editor(editor, _) = self {
+                if !text.trim().is_empty() {
+                    let _ = editor.add_history_entry(text.as_str());
+                }
+            }
+            return Some(text);
+        }
+    }
+
+    fn read_raw(&mut self, prompt: &str) -> Input {
+        match self {
+            Reader::Editor(editor, _) => match editor.readline(prompt) {
+                Ok(line) => Input::Line(line),
+                Err(ReadlineError::Interrupted) => Input::Interrupted,
+                Err(_) => Input::End
+            },
+            Reader::Plain(lines) => match lines.next() {
+                Some(Ok(line)) => Input::Line(line),
+                _ => Input::End
+            }
+        }
+    }
+
+    pub fn finish(&mut self) -> Result<(), String> {
+        match self {
+            Reader::Editor(editor, Some(path)) => {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir).map_err(|e| format!("Could not save history: {}", e))?;
+                }
+                editor.save_history(path).map_err(|e| format!("Could not save history: {}", e))
+            },
+            _ => Ok(())
+        }
+    }
+}
+
+pub fn color() -> bool {
+    io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+fn history_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("rustkalkulator").join("history"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn complete_variable() {
+        let names = vec![String::from("rate"), String::from("ratio"), String::from("total")];
+        assert_eq!(complete("2 * ra", 6, &names), (4, vec![String::from("rate"), String::from("ratio")]));
+        assert_eq!(complete("tot", 3, &names), (0, vec![String::from("total")]));
+        assert_eq!(complete("1 + ", 4, &names), (4, Vec::new()));
+    }
+
+    #[test]
+    fn complete_command() {
+        assert_eq!(complete(":sa", 3, &[]), (1, vec![String::from("save")]));
+        assert_eq!(complete(":", 1, &[]).1.len(), COMMANDS.len());
+    }
+}
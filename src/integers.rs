@@ -0,0 +1,400 @@
+use super::decimal;
+use super::value::{self, Value};
+use num_bigint::{BigInt, BigUint, Sign};
+use std::convert::TryFrom;
+
+// Trial division covers factors up to this size before Pollard's rho takes
+// over for what is left.
+const SMALL_FACTORS: u128 = 10_000;
+
+// Results past i128 go on as exact decimals, which end below 10^10000.
+const MAX_BITS: u64 = 33_219;
+
+// How long Pollard's rho looks for a factor before giving up.
+const RHO_STEPS: usize = 1_000_000;
+
+// Miller-Rabin with these bases is exact below 3.3 * 10^24 and has never
+// been known to fail above it.
+const WITNESSES: &[u128] = &[2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+// Whole numbers, including decimals too large for an integer, like 40!.
+fn integer(name: &str, x: &Value) -> Result<BigInt, String> {
+    let whole = match x {
+        Value::Int(n) => Some(BigInt::from(*n)),
+        Value::Decimal(x) => x.whole_number(),
+        _ => None
+    };
+    whole.ok_or_else(|| format!("{} needs whole numbers", name))
+}
+
+fn natural(name: &str, x: &Value) -> Result<BigUint, String> {
+    match integer(name, x)? {
+        n if n.sign() == Sign::Minus => Err(format!("{} needs numbers that are not negative, got {}", name, n)),
+        n => Ok(n.magnitude().clone())
+    }
+}
+
+// How many steps a loop takes; past u128 the result could not fit anyway.
+fn count(x: &BigUint) -> Result<u128, String> {
+    u128::try_from(x).map_err(|_| value::overflow())
+}
+
+fn arguments<const N: usize>(name: &str, arguments: Vec<Value>) -> Result<[Value; N], String> {
+    <[Value; N]>::try_from(arguments).map_err(|arguments| match N {
+        1 => format!("{} takes 1 argument, got {}", name, arguments.len()),
+        _ => format!("{} takes {} arguments, got {}", name, N, arguments.len())
+    })
+}
+
+fn exact(x: BigInt) -> Result<Value, String> {
+    match i128::try_from(&x) {
+        Ok(n) => Ok(Value::Int(n)),
+        Err(_) if x.bits() <= MAX_BITS => Ok(Value::Decimal(decimal::whole(x))),
+        Err(_) => Err(value::overflow())
+    }
+}
+
+fn truth(x: bool) -> Value {
+    Value::Int(x as i128)
+}
+
+pub fn factorial(x: Value) -> Result<Value, String> {
+    let n = natural("Factorial", &x)?;
+    let r = count(&n)?;
+    permutations(&n, r)
+}
+
+// n! / (n - r)!, multiplied out from the top so it can stop as soon as it
+// grows too large.
+fn permutations(n: &BigUint, r: u128) -> Result<Value, String> {
+    let mut product = BigUint::from(1u8);
+    for k in 0..r {
+        product *= n - k;
+        if product.bits() > MAX_BITS {
+            return Err(value::overflow());
+        }
+    }
+    exact(BigInt::from(product))
+}
+
+pub fn ncr(arguments: Vec<Value>) -> Result<Value, String> {
+    let [n, r] = self::arguments("nCr", arguments)?;
+    let (n, r) = (natural("nCr", &n)?, natural("nCr", &r)?);
+    if r > n {
+        return Ok(Value::Int(0));
+    }
+    // Each partial product is itself a binomial coefficient, so the division
+    // is exact and the numbers only grow.
+    let r = count(&std::cmp::min(&n - &r, r))?;
+    let mut result = BigUint::from(1u8);
+    for k in 0..r {
+        result = result * (&n - k) / (k + 1);
+        if result.bits() > MAX_BITS {
+            return Err(value::overflow());
+        }
+    }
+    exact(BigInt::from(result))
+}
+
+pub fn npr(arguments: Vec<Value>) -> Result<Value, String> {
+    let [n, r] = self::arguments("nPr", arguments)?;
+    let (n, r) = (natural("nPr", &n)?, natural("nPr", &r)?);
+    match r > n {
+        true => Ok(Value::Int(0)),
+        false => permutations(&n, count(&r)?)
+    }
+}
+
+fn gcd_of(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+fn big_gcd(mut a: BigUint, mut b: BigUint) -> BigUint {
+    while b.bits() > 0 {
+        (a, b) = (b.clone(), a % b);
+    }
+    a
+}
+
+// Over any number of arguments, or one list of them.
+fn integers(name: &str, arguments: Vec<Value>) -> Result<Vec<BigInt>, String> {
+    let items = match <[Value; 1]>::try_from(arguments) {
+        Ok([Value::List(items)]) => items,
+        Ok([x]) => vec![x],
+        Err(arguments) => arguments
+    };
+    if items.is_empty() {
+        return Err(format!("{} of an empty list", name));
+    }
+    items.iter().map(|x| integer(name, x)).collect()
+}
+
+pub fn gcd(arguments: Vec<Value>) -> Result<Value, String> {
+    let divisor = integers("gcd", arguments)?.iter().fold(BigUint::ZERO, |a, b| big_gcd(a, b.magnitude().clone()));
+    exact(BigInt::from(divisor))
+}
+
+pub fn lcm(arguments: Vec<Value>) -> Result<Value, String> {
+    let mut multiple = BigUint::from(1u8);
+    for n in integers("lcm", arguments)? {
+        let n = n.magnitude();
+        if n.bits() == 0 {
+            return Ok(Value::Int(0));
+        }
+        multiple = &multiple / big_gcd(multiple.clone(), n.clone()) * n;
+        if multiple.bits() > MAX_BITS {
+            return Err(value::overflow());
+        }
+    }
+    exact(BigInt::from(multiple))
+}
+
+fn mulmod(a: u128, b: u128, m: u128) -> u128 {
+    let product = BigUint::from(a) * b % m;
+    u128::try_from(&product).unwrap()
+}
+
+fn powmod(b: u128, e: u128, m: u128) -> u128 {
+    let power = BigUint::from(b).modpow(&BigUint::from(e), &BigUint::from(m));
+    u128::try_from(&power).unwrap()
+}
+
+fn is_prime(n: u128) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for p in WITNESSES {
+        if n.is_multiple_of(*p) {
+            return n == *p;
+        }
+    }
+    let (mut d, mut s) = (n - 1, 0);
+    while d.is_multiple_of(2) {
+        d /= 2;
+        s += 1;
+    }
+    WITNESSES.iter().all(|a| {
+        let mut x = powmod(*a, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..s {
+            x = mulmod(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+// Miller-Rabin for numbers past u128, with the same bases.
+fn is_big_prime(n: &BigUint) -> bool {
+    let n = match u128::try_from(n) {
+        Ok(n) => return is_prime(n),
+        Err(_) => n
+    };
+    if WITNESSES.iter().any(|p| (n % *p).bits() == 0) {
+        return false;
+    }
+    let one = BigUint::from(1u8);
+    let m = n - &one;
+    let s = m.trailing_zeros().unwrap_or(0);
+    let d = &m >> s;
+    WITNESSES.iter().all(|a| {
+        let mut x = BigUint::from(*a).modpow(&d, n);
+        if x == one || x == m {
+            return true;
+        }
+        for _ in 1..s {
+            x = &x * &x % n;
+            if x == m {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+pub fn isprime(x: Value) -> Result<Value, String> {
+    let n = integer("isprime", &x)?;
+    Ok(truth(n.sign() == Sign::Plus && is_big_prime(n.magnitude())))
+}
+
+pub fn nextprime(x: Value) -> Result<Value, String> {
+    let mut n = integer("nextprime", &x)?.max(BigInt::from(1u8));
+    loop {
+        n += 1u8;
+        if is_big_prime(n.magnitude()) {
+            return exact(n);
+        }
+    }
+}
+
+// Pollard's rho with Floyd's cycle finding, which finds some factor of a
+// composite number that has no small ones.
+fn rho(n: u128) -> Option<u128> {
+    for c in 1..20 {
+        let f = |x: u128| (mulmod(x, x, n) + c) % n;
+        let (mut x, mut y) = (2, 2);
+        for _ in 0..RHO_STEPS {
+            x = f(x);
+            y = f(f(y));
+            match gcd_of(x.abs_diff(y), n) {
+                1 => continue,
+                d if d == n => break,
+                d => return Some(d)
+            }
+        }
+    }
+    None
+}
+
+fn prime_factors(mut n: u128) -> Result<Vec<u128>, String> {
+    let original = n;
+    let mut factors = Vec::new();
+    let mut p = 2;
+    while p <= SMALL_FACTORS && p * p <= n {
+        while n.is_multiple_of(p) {
+            factors.push(p);
+            n /= p;
+        }
+        p += if p == 2 { 1 } else { 2 };
+    }
+    let mut rest = vec![n];
+    while let Some(m) = rest.pop() {
+        match m {
+            1 => {},
+            m if is_prime(m) => factors.push(m),
+            m => match rho(m) {
+                Some(d) => rest.extend([d, m / d]),
+                None => return Err(format!("Could not factor {}", original))
+            }
+        }
+    }
+    factors.sort_unstable();
+    Ok(factors)
+}
+
+// Numbers past u128 lose their small factors first, and what is left has
+// to fit in a u128 or be prime.
+fn big_prime_factors(n: &BigUint) -> Result<Vec<BigUint>, String> {
+    if let Ok(n) = u128::try_from(n) {
+        return Ok(prime_factors(n)?.into_iter().map(BigUint::from).collect());
+    }
+    let mut rest = n.clone();
+    let mut factors = Vec::new();
+    for p in 2..=SMALL_FACTORS {
+        while (&rest % p).bits() == 0 {
+            factors.push(BigUint::from(p));
+            rest /= p;
+        }
+    }
+    match u128::try_from(&rest) {
+        Ok(m) => factors.extend(prime_factors(m)?.into_iter().map(BigUint::from)),
+        Err(_) if is_big_prime(&rest) => factors.push(rest),
+        Err(_) => return Err(format!("Could not factor {}", n))
+    }
+    factors.sort();
+    Ok(factors)
+}
+
+pub fn factor(x: Value) -> Result<Value, String> {
+    let n = match integer("factor", &x)? {
+        n if n.sign() != Sign::Plus => return Err(format!("Only positive numbers can be factored, got {}", n)),
+        n => n.magnitude().clone()
+    };
+    big_prime_factors(&n)?.into_iter().map(|p| exact(BigInt::from(p))).collect::<Result<_, _>>().map(Value::List)
+}
+
+// Euler's totient, from the distinct prime factors.
+pub fn totient(x: Value) -> Result<Value, String> {
+    let n = match integer("totient", &x)? {
+        n if n.sign() != Sign::Plus => return Err(format!("totient needs a positive number, got {}", n)),
+        n => n.magnitude().clone()
+    };
+    let mut factors = big_prime_factors(&n)?;
+    factors.dedup();
+    let count = factors.iter().fold(n, |count, p| count / p * (p - 1u8));
+    exact(BigInt::from(count))
+}
+
+pub fn modpow(arguments: Vec<Value>) -> Result<Value, String> {
+    let [b, e, m] = self::arguments("modpow", arguments)?;
+    let (b, e, m) = (integer("modpow", &b)?, natural("modpow", &e)?, integer("modpow", &m)?);
+    if m.sign() != Sign::Plus {
+        return Err(format!("modpow needs a positive modulus, got {}", m));
+    }
+    let base = (b % &m + &m) % &m;
+    exact(base.modpow(&BigInt::from(e), &m))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ints(items: &[i128]) -> Vec<Value> {
+        items.iter().map(|x| Value::Int(*x)).collect()
+    }
+
+    #[test]
+    fn combinatorics() {
+        assert_eq!(factorial(Value::Int(0)), Ok(Value::Int(1)));
+        assert_eq!(factorial(Value::Int(20)), Ok(Value::Int(2432902008176640000)));
+        assert_eq!(factorial(Value::Int(33)), Ok(Value::Int(8683317618811886495518194401280000000)));
+        assert_eq!(factorial(Value::Int(40)).map(|x| x.to_string()), Ok(String::from("815915283247897734345611269596115894272000000000")));
+        assert!(factorial(Value::Int(3248)).is_ok());
+        assert_eq!(factorial(Value::Int(3249)), Err(String::from("Integer overflow")));
+        assert_eq!(factorial(Value::Int(-1)), Err(String::from("Factorial needs numbers that are not negative, got -1")));
+        assert_eq!(factorial(Value::Real(2.5)), Err(String::from("Factorial needs whole numbers")));
+        assert_eq!(ncr(ints(&[5, 2])), Ok(Value::Int(10)));
+        assert_eq!(ncr(ints(&[2, 5])), Ok(Value::Int(0)));
+        assert_eq!(ncr(ints(&[1000, 998])), Ok(Value::Int(499500)));
+        assert_eq!(ncr(ints(&[120, 60])), Ok(Value::Int(96614908840363322603893139521372656)));
+        assert_eq!(ncr(ints(&[200, 100])).map(|x| x.to_string()), Ok(String::from("90548514656103281165404177077484163874504589675413336841320")));
+        assert_eq!(npr(ints(&[5, 2])), Ok(Value::Int(20)));
+        assert_eq!(npr(ints(&[40, 30])).map(|x| x.to_string()), Ok(String::from("224844379201911853600532206127677440000000")));
+        assert_eq!(npr(ints(&[5])), Err(String::from("nPr takes 2 arguments, got 1")));
+    }
+
+    #[test]
+    fn divisors() {
+        assert_eq!(gcd(ints(&[12, -18])), Ok(Value::Int(6)));
+        assert_eq!(gcd(vec![Value::List(ints(&[0, 0]))]), Ok(Value::Int(0)));
+        assert_eq!(lcm(ints(&[4, 6, 10])), Ok(Value::Int(60)));
+        assert_eq!(lcm(ints(&[i128::MAX, i128::MAX - 1])).map(|x| x.to_string()), Ok(String::from("28948022309329048855892746252171976962807072616028733314669334090830630092802")));
+        let forty = factorial(Value::Int(40)).unwrap();
+        assert_eq!(gcd(vec![forty.clone(), Value::Int(6)]), Ok(Value::Int(6)));
+        assert_eq!(gcd(vec![forty.clone(), Value::Int(1 << 100)]), Ok(Value::Int(274877906944)));
+        assert_eq!(modpow(vec![forty, Value::Int(2), Value::Int(1_000_000_007)]), Ok(Value::Int(485811293)));
+        assert_eq!(modpow(ints(&[4, 13, 497])), Ok(Value::Int(445)));
+        assert_eq!(modpow(ints(&[-2, 3, 5])), Ok(Value::Int(2)));
+        assert_eq!(modpow(ints(&[i128::MAX, i128::MAX, 1_000_000_007])), Ok(Value::Int(919457838)));
+        assert!(modpow(ints(&[2, -1, 5])).is_err());
+    }
+
+    #[test]
+    fn primes() {
+        assert_eq!(isprime(Value::Int(1)), Ok(Value::Int(0)));
+        assert_eq!(isprime(Value::Int(97)), Ok(Value::Int(1)));
+        assert_eq!(isprime(Value::Int(561)), Ok(Value::Int(0)));
+        assert_eq!(isprime(Value::Int(170141183460469231731687303715884105727)), Ok(Value::Int(1)));
+        assert_eq!(nextprime(Value::Int(-5)), Ok(Value::Int(2)));
+        assert_eq!(nextprime(Value::Int(13)), Ok(Value::Int(17)));
+        assert_eq!(factor(Value::Int(360)), Ok(Value::List(ints(&[2, 2, 2, 3, 3, 5]))));
+        assert_eq!(factor(Value::Int(1)), Ok(Value::List(Vec::new())));
+        assert_eq!(factor(Value::Int(1000000016000000063)), Ok(Value::List(ints(&[1000000007, 1000000009]))));
+        assert!(factor(Value::Int(0)).is_err());
+        assert_eq!(totient(Value::Int(36)), Ok(Value::Int(12)));
+        assert_eq!(totient(Value::Int(1)), Ok(Value::Int(1)));
+        let big = |text: &str| Value::Decimal(decimal::parse(text).unwrap());
+        assert_eq!(isprime(big("6864797660130609714981900799081393217269435300143305409394463459185543183397656052122559640661454554977296311391480858037121987999716643812574028291115057151")), Ok(Value::Int(1)));
+        assert_eq!(isprime(big("815915283247897734345611269596115894272000000001")), Ok(Value::Int(0)));
+        assert_eq!(nextprime(big("1361129467683753853853498429727072845824")).map(|x| x.to_string()), Ok(String::from("1361129467683753853853498429727072845993")));
+        assert_eq!(factor(big("1361129467683753853853498429727072845824")), Ok(Value::List(vec![Value::Int(2); 130])));
+        assert_eq!(totient(big("1361129467683753853853498429727072845824")).map(|x| x.to_string()), Ok(String::from("680564733841876926926749214863536422912")));
+    }
+}
@@ -1,145 +1,277 @@
+use num_bigint::BigUint;
+
+const OPERATORS: &str = "+-*/@^&|~<>!%";
+
 #[derive(Debug)]
 pub struct Tokens<'a> {
     pub tokens: Vec<Token>,
-    input: std::str::Chars<'a>
+    pub spans: Vec<Span>,
+    input: std::iter::Peekable<std::str::Chars<'a>>,
+    position: usize,
 }
 
+// The `into_*` helpers read one token into the list, so they borrow the lexer.
+#[allow(clippy::wrong_self_convention)]
 impl<'a> Tokens<'a> {
     pub fn new(characters: std::str::Chars) -> Tokens {
         Tokens {
             tokens: Vec::new(),
-            input: characters
+            spans: Vec::new(),
+            input: characters.peekable(),
+            position: 0,
         }
     }
+
     pub fn tokenize(&mut self) {
-        match self.input.next() {
-            Some(c) => { 
-                match c {
-                    c if "+-*/".contains(c) => {
-                        self.into_operator(c);
-                    },
-                    '=' => { 
-                        self.tokens.push(Token::Assign);
-                        self.tokenize();
-                    },
-                    '(' => {
-                        self.tokens.push(Token::LParen);
-                        self.tokenize();
-                    }
-                    ')' => {
-                        self.tokens.push(Token::RParen);
-                        self.tokenize();
-                    },
-                    c if c.is_digit(10) => {
-                        let num = String::new();
-                        self.into_number(c, num)
-                    },
-                    c if c.is_alphabetic() => {
-                        let ident = String::new();
-                        self.into_identifier(c, ident);
-                    },
-                    c if c.is_whitespace() => self.tokenize(),
-                    c => self.tokens.push(Token::Error(c.to_string())),
-                };
-            },
-            None => self.tokens.push(Token::End)
+        self.scan(true);
+    }
+
+    // Keeps going past bad characters so that every part of a half-typed
+    // line is covered by some token, which is what the highlighter needs.
+    pub fn tokenize_partial(&mut self) {
+        self.scan(false);
+    }
+
+    fn scan(&mut self, stop_on_error: bool) {
+        while let Some(c) = self.next() {
+            let start = self.position - 1;
+            let token = match c {
+                c if OPERATORS.contains(c) => self.into_operator(c),
+                '=' => Token::Assign,
+                ':' if self.input.peek() == Some(&'=') => {
+                    self.next();
+                    Token::Define
+                },
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                ',' => Token::Comma,
+                '[' => Token::LBracket,
+                ']' => Token::RBracket,
+                '.' if self.input.peek() == Some(&'.') => {
+                    self.next();
+                    Token::Op(Operator::Range)
+                },
+                c if c.is_ascii_digit() => self.into_number(c),
+                c if c.is_alphabetic() || c == '_' => self.into_identifier(c),
+                '$' => self.into_output(c),
+                c if c.is_whitespace() => continue,
+                c => Token::Error(c.to_string()),
+            };
+            let error = matches!(token, Token::Error(_) | Token::Overflow(_));
+            self.push(token, start);
+            if error && stop_on_error {
+                return;
+            }
         }
+        self.push(Token::End, self.position);
     }
 
-    fn into_operator(&mut self, o: char) {
+    fn next(&mut self) -> Option<char> {
+        let c = self.input.next();
+        if c.is_some() {
+            self.position += 1;
+        }
+        c
+    }
+
+    fn push(&mut self, token: Token, start: usize) {
+        self.tokens.push(token);
+        self.spans.push(Span { start, end: self.position });
+    }
+
+    fn into_operator(&mut self, o: char) -> Token {
         let op = match o {
             '-' => Operator::Minus,
             '*' => Operator::Times,
             '/' => Operator::Divide,
+            '^' => Operator::Power,
+            '@' => Operator::MatMul,
+            '&' => Operator::And,
+            '|' => Operator::Or,
+            '~' => Operator::Not,
+            '!' => Operator::Factorial,
+            '%' => Operator::Percent,
+            '<' | '>' if self.input.peek() != Some(&o) => return Token::Error(o.to_string()),
+            '<' => {
+                self.next();
+                Operator::ShiftLeft
+            },
+            '>' => {
+                self.next();
+                Operator::ShiftRight
+            },
             _ => Operator::Plus,
         };
-        self.tokens.push(Token::Op(op));
-        self.tokenize();
-    }
-
-    fn into_number(&mut self, n: char, mut num: String) {
-        num.push(n);
-        match self.input.next() {
-            Some(c) => {
-                match c {
-                    c if c.is_digit(10) => self.into_number(c, num),
-                    c if c.is_whitespace() => {
-                        self.tokens.push(Token::Num(num.parse::<i32>().unwrap()));
-                        self.tokenize();
-                    },
-                    c if "+-*/".contains(c) => {
-                        self.tokens.push(Token::Num(num.parse::<i32>().unwrap()));
-                        self.into_operator(c);
-                    },
-                    '=' => { 
-                        self.tokens.push(Token::Num(num.parse::<i32>().unwrap()));
-                        self.tokens.push(Token::Assign);
-                        self.tokenize();
-                    },
-                    ')' => {
-                        self.tokens.push(Token::Num(num.parse::<i32>().unwrap()));
-                        self.tokens.push(Token::RParen);
-                        self.tokenize();
-                    },
-                    c => { 
-                        num.push(c);
-                        self.tokens.push(Token::Error(num));
-                    }
-                };
-            },
-            None => {
-                self.tokens.push(Token::Num(num.parse::<i32>().unwrap()));
-                self.tokens.push(Token::End);
+        Token::Op(op)
+    }
+
+    fn into_number(&mut self, n: char) -> Token {
+        let radix = match (n, self.input.peek()) {
+            ('0', Some('x')) | ('0', Some('X')) => 16,
+            ('0', Some('b')) | ('0', Some('B')) => 2,
+            ('0', Some('o')) | ('0', Some('O')) => 8,
+            _ => 10
+        };
+        if radix != 10 && self.digit_after(1, radix) {
+            return self.into_radix(n, radix);
+        }
+        let mut num = n.to_string();
+        self.into_digits(&mut num, 10);
+        let mut real = false;
+        if self.input.peek() == Some(&'.') && self.digit_after(1, 10) {
+            real = true;
+            num.push('.');
+            self.next();
+            self.into_digits(&mut num, 10);
+        }
+        if matches!(self.input.peek(), Some('e') | Some('E')) && (self.digit_after(1, 10) || (self.sign_after(1) && self.digit_after(2, 10))) {
+            real = true;
+            num.push('e');
+            self.next();
+            if self.sign_after(0) {
+                num.push(self.next().unwrap());
             }
+            self.into_digits(&mut num, 10);
+        }
+        // A trailing `i` makes an imaginary literal, as in `3+4i`.
+        let imaginary = self.input.peek() == Some(&'i') && !self.input.clone().nth(1).is_some_and(|c| c.is_alphanumeric() || c == '_');
+        if imaginary {
+            self.next();
+        }
+        // A name straight after a number multiplies it, as in `2x` or `5kg`,
+        // but a base prefix without digits, like `0x`, is still a mistake.
+        let named = radix == 10 && self.input.peek().is_some_and(|c| c.is_alphabetic());
+        let num = match named {
+            true => Ok(num),
+            false => self.delimit(num)
         };
+        match num {
+            Ok(num) if imaginary => match num.replace('_', "").parse::<f64>() {
+                Ok(x) if x.is_finite() => Token::Imag(x),
+                _ => Token::Overflow(num + "i")
+            },
+            // The text is kept as written, so decimal mode can read it exactly.
+            Ok(num) if real => match num.replace('_', "") {
+                text if text.parse::<f64>().is_ok_and(f64::is_finite) => Token::Decimal(text),
+                _ => Token::Overflow(num)
+            },
+            // Integers past i128 are read exactly as decimals, like 40!.
+            Ok(num) => {
+                let text = num.replace('_', "");
+                match text.parse::<i128>() {
+                    Ok(n) => Token::Num(n),
+                    Err(_) => Token::Decimal(text)
+                }
+            },
+            Err(e) => e
+        }
     }
 
-    fn into_identifier(&mut self, i: char, mut ident: String) {
-        ident.push(i);
-        match self.input.next() {
-            Some(c) => {
-                match c {
-                    c if c.is_alphabetic() => self.into_identifier(c, ident),
-                    c if c.is_whitespace() => {
-                        self.tokens.push(Token::Ident(ident));
-                        self.tokenize();
-                    },
-                    c if "+-*/".contains(c) => {
-                        self.tokens.push(Token::Ident(ident));
-                        self.into_operator(c);
-                    },
-                    '=' => { 
-                        self.tokens.push(Token::Ident(ident));
-                        self.tokens.push(Token::Assign);
-                        self.tokenize();
-                    },
-                    ')' => {
-                        self.tokens.push(Token::Ident(ident));
-                        self.tokens.push(Token::RParen);
-                        self.tokenize();
-                    },
-                    c => {
-                        ident.push(c);
-                        self.tokens.push(Token::Error(ident));
-                    }
-                };
+    fn into_radix(&mut self, zero: char, radix: u32) -> Token {
+        let mut num = zero.to_string();
+        num.push(self.next().unwrap());
+        self.into_digits(&mut num, radix);
+        match self.delimit(num) {
+            Ok(num) => match i128::from_str_radix(&num[2..].replace('_', ""), radix) {
+                Ok(n) => Token::Num(n),
+                Err(_) => match BigUint::parse_bytes(num[2..].replace('_', "").as_bytes(), radix) {
+                    Some(n) => Token::Decimal(n.to_string()),
+                    None => Token::Overflow(num)
+                }
             },
-            None => { 
-                self.tokens.push(Token::Ident(ident));
-                self.tokens.push(Token::End);
+            Err(e) => e
+        }
+    }
+
+    // Digits may be separated by single underscores, as in `1_000_000`.
+    fn into_digits(&mut self, num: &mut String, radix: u32) {
+        loop {
+            match self.input.peek().copied() {
+                Some(c) if c.is_digit(radix) => num.push(c),
+                Some('_') if self.digit_after(1, radix) => num.push('_'),
+                _ => return
             }
-        };
+            self.next();
+        }
     }
+
+    fn digit_after(&self, skip: usize, radix: u32) -> bool {
+        self.input.clone().nth(skip).is_some_and(|c| c.is_digit(radix))
+    }
+
+    fn sign_after(&self, skip: usize) -> bool {
+        matches!(self.input.clone().nth(skip), Some('+') | Some('-'))
+    }
+
+    fn into_identifier(&mut self, i: char) -> Token {
+        let mut ident = i.to_string();
+        while let Some(c) = self.input.peek().filter(|c| c.is_alphabetic() || **c == '_').copied() {
+            self.next();
+            ident.push(c);
+        }
+        match self.delimit(ident) {
+            Ok(ident) if ident == "xor" => Token::Op(Operator::Xor),
+            Ok(ident) => Token::Ident(ident),
+            Err(e) => e
+        }
+    }
+
+    fn into_output(&mut self, d: char) -> Token {
+        let mut output = d.to_string();
+        while let Some(c) = self.input.peek().filter(|c| c.is_ascii_digit()).copied() {
+            self.next();
+            output.push(c);
+        }
+        if output.len() == 1 {
+            if let Some(c) = self.next() {
+                output.push(c);
+            }
+            return Token::Error(output);
+        }
+        match self.delimit(output) {
+            Ok(output) => Token::Ident(output),
+            Err(e) => e
+        }
+    }
+
+    // A word must be followed by whitespace, an operator, `=`, a parenthesis
+    // or bracket, `,`, `..` or the end of input; anything else is glued onto
+    // it and reported as an error.
+    fn delimit(&mut self, word: String) -> Result<String, Token> {
+        let range = self.input.clone().take(2).eq("..".chars());
+        match self.input.peek() {
+            Some(c) if !(c.is_whitespace() || OPERATORS.contains(*c) || "=()[],".contains(*c) || range) => {
+                let mut word = word;
+                word.push(*c);
+                self.next();
+                Err(Token::Error(word))
+            },
+            _ => Ok(word)
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Token {
     LParen,
     RParen,
+    Comma,
+    LBracket,
+    RBracket,
     Assign,
+    Define,
     Op(Operator),
     Ident(String),
-    Num(i32),
+    Num(i128),
+    Decimal(String),
+    Imag(f64),
+    Overflow(String),
     Error(String),
     End,
 }
@@ -150,6 +282,17 @@ pub enum Operator {
     Minus,
     Times,
     Divide,
+    MatMul,
+    Power,
+    And,
+    Or,
+    Xor,
+    Not,
+    Factorial,
+    Percent,
+    ShiftLeft,
+    ShiftRight,
+    Range,
 }
 
 #[cfg(test)]
@@ -200,11 +343,11 @@ mod tests {
 
     #[test]
     fn invalid_num() {
-        let string = "1invalid";
+        let string = "1#invalid";
         let chars = string.chars();
         let mut tokenizer = Tokens::new(chars);
         tokenizer.tokenize();
-        assert_eq!(tokenizer.tokens, vec![Token::Error(String::from("1i"))]);
+        assert_eq!(tokenizer.tokens, vec![Token::Error(String::from("1#"))]);
     }
 
     #[test]
@@ -236,4 +379,360 @@ mod tests {
         tokenizer.tokenize();
         assert_eq!(tokenizer.tokens, valid_tokens);
     }
+
+    #[test]
+    fn valid_underscore_ident() {
+        let string = "_ + k_B";
+        let chars = string.chars();
+        let valid_tokens = vec![
+            Token::Ident(String::from("_")),
+            Token::Op(Operator::Plus),
+            Token::Ident(String::from("k_B")),
+            Token::End
+        ];
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, valid_tokens);
+    }
+
+    #[test]
+    fn valid_output() {
+        let string = "$12*2";
+        let chars = string.chars();
+        let valid_tokens = vec![
+            Token::Ident(String::from("$12")),
+            Token::Op(Operator::Times),
+            Token::Num(2),
+            Token::End
+        ];
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, valid_tokens);
+    }
+
+    #[test]
+    fn invalid_output() {
+        let string = "$x";
+        let chars = string.chars();
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, vec![Token::Error(String::from("$x"))]);
+    }
+
+    #[test]
+    fn token_spans() {
+        let string = "ab = (12+$3)";
+        let chars = string.chars();
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        let spans: Vec<(usize, usize)> = tokenizer.spans.iter().map(|s| (s.start, s.end)).collect();
+        assert_eq!(spans, vec![(0, 2), (3, 4), (5, 6), (6, 8), (8, 9), (9, 11), (11, 12), (12, 12)]);
+    }
+
+    #[test]
+    fn error_span() {
+        let string = "1 + 2#";
+        let chars = string.chars();
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens.last(), Some(&Token::Error(String::from("2#"))));
+        assert_eq!(tokenizer.spans.last(), Some(&Span { start: 4, end: 6 }));
+    }
+
+    #[test]
+    fn partial_input() {
+        let string = "1# + $ (";
+        let chars = string.chars();
+        let valid_tokens = vec![
+            Token::Error(String::from("1#")),
+            Token::Op(Operator::Plus),
+            Token::Error(String::from("$ ")),
+            Token::LParen,
+            Token::End
+        ];
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize_partial();
+        assert_eq!(tokenizer.tokens, valid_tokens);
+        assert_eq!(tokenizer.spans.last(), Some(&Span { start: 8, end: 8 }));
+    }
+
+    #[test]
+    fn valid_real() {
+        let string = "1.5+2e3-4.25E-2";
+        let chars = string.chars();
+        let valid_tokens = vec![
+            Token::Decimal(String::from("1.5")),
+            Token::Op(Operator::Plus),
+            Token::Decimal(String::from("2e3")),
+            Token::Op(Operator::Minus),
+            Token::Decimal(String::from("4.25e-2")),
+            Token::End
+        ];
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, valid_tokens);
+    }
+
+    #[test]
+    fn invalid_real() {
+        let string = "1.x";
+        let chars = string.chars();
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, vec![Token::Error(String::from("1."))]);
+    }
+
+    #[test]
+    fn valid_radix() {
+        let string = "0xFF+0b1010*0o755";
+        let chars = string.chars();
+        let valid_tokens = vec![
+            Token::Num(255),
+            Token::Op(Operator::Plus),
+            Token::Num(10),
+            Token::Op(Operator::Times),
+            Token::Num(493),
+            Token::End
+        ];
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, valid_tokens);
+    }
+
+    #[test]
+    fn invalid_radix() {
+        let string = "0x";
+        let chars = string.chars();
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, vec![Token::Error(String::from("0x"))]);
+        let string = "0b102";
+        let chars = string.chars();
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, vec![Token::Error(String::from("0b102"))]);
+    }
+
+    #[test]
+    fn digit_separators() {
+        let string = "1_000_000 0x_1 2_000.000_5 3__0";
+        let chars = string.chars();
+        let valid_tokens = vec![
+            Token::Num(1000000),
+            Token::Error(String::from("0x")),
+            Token::Error(String::from("_1")),
+            Token::Decimal(String::from("2000.0005")),
+            Token::Error(String::from("3_")),
+            Token::Error(String::from("_0")),
+            Token::End
+        ];
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize_partial();
+        assert_eq!(tokenizer.tokens, valid_tokens);
+    }
+
+    #[test]
+    fn number_overflow() {
+        let string = "1 + 0x1_0000_0000_0000_0000_0000_0000_0000_0000";
+        let chars = string.chars();
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens[2], Token::Decimal(String::from("340282366920938463463374607431768211456")));
+        let string = "999999999999999999999999999999999999999";
+        let chars = string.chars();
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, vec![Token::Decimal(String::from("999999999999999999999999999999999999999")), Token::End]);
+        let string = "1e999";
+        let chars = string.chars();
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, vec![Token::Overflow(String::from("1e999"))]);
+    }
+
+    #[test]
+    fn valid_bitwise() {
+        let string = "~a&b|c xor 1<<2>>3";
+        let chars = string.chars();
+        let valid_tokens = vec![
+            Token::Op(Operator::Not),
+            Token::Ident(String::from("a")),
+            Token::Op(Operator::And),
+            Token::Ident(String::from("b")),
+            Token::Op(Operator::Or),
+            Token::Ident(String::from("c")),
+            Token::Op(Operator::Xor),
+            Token::Num(1),
+            Token::Op(Operator::ShiftLeft),
+            Token::Num(2),
+            Token::Op(Operator::ShiftRight),
+            Token::Num(3),
+            Token::End
+        ];
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, valid_tokens);
+    }
+
+    #[test]
+    fn invalid_shift() {
+        let string = "1 < 2";
+        let chars = string.chars();
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, vec![Token::Num(1), Token::Error(String::from("<"))]);
+    }
+
+    #[test]
+    fn valid_power() {
+        let string = "m/s^2";
+        let chars = string.chars();
+        let valid_tokens = vec![
+            Token::Ident(String::from("m")),
+            Token::Op(Operator::Divide),
+            Token::Ident(String::from("s")),
+            Token::Op(Operator::Power),
+            Token::Num(2),
+            Token::End
+        ];
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, valid_tokens);
+    }
+
+    #[test]
+    fn valid_imaginary() {
+        let string = "3+4i, 2.5i i 2in";
+        let chars = string.chars();
+        let valid_tokens = vec![
+            Token::Num(3),
+            Token::Op(Operator::Plus),
+            Token::Imag(4.0),
+            Token::Comma,
+            Token::Imag(2.5),
+            Token::Ident(String::from("i")),
+            Token::Num(2),
+            Token::Ident(String::from("in")),
+            Token::End
+        ];
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize_partial();
+        assert_eq!(tokenizer.tokens, valid_tokens);
+    }
+
+    #[test]
+    fn valid_brackets() {
+        let string = "[[1,2]] @ m[0]";
+        let chars = string.chars();
+        let valid_tokens = vec![
+            Token::LBracket,
+            Token::LBracket,
+            Token::Num(1),
+            Token::Comma,
+            Token::Num(2),
+            Token::RBracket,
+            Token::RBracket,
+            Token::Op(Operator::MatMul),
+            Token::Ident(String::from("m")),
+            Token::LBracket,
+            Token::Num(0),
+            Token::RBracket,
+            Token::End
+        ];
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, valid_tokens);
+    }
+
+    #[test]
+    fn valid_range() {
+        let string = "1..10 1.5..n";
+        let chars = string.chars();
+        let valid_tokens = vec![
+            Token::Num(1),
+            Token::Op(Operator::Range),
+            Token::Num(10),
+            Token::Decimal(String::from("1.5")),
+            Token::Op(Operator::Range),
+            Token::Ident(String::from("n")),
+            Token::End
+        ];
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, valid_tokens);
+    }
+
+    #[test]
+    fn valid_define() {
+        let string = "y := x";
+        let chars = string.chars();
+        let valid_tokens = vec![
+            Token::Ident(String::from("y")),
+            Token::Define,
+            Token::Ident(String::from("x")),
+            Token::End
+        ];
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, valid_tokens);
+    }
+
+    #[test]
+    fn invalid_define() {
+        let string = "y : x";
+        let chars = string.chars();
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens.last(), Some(&Token::Error(String::from(":"))));
+    }
+
+    #[test]
+    fn valid_factorial() {
+        let string = "n!!";
+        let chars = string.chars();
+        let valid_tokens = vec![
+            Token::Ident(String::from("n")),
+            Token::Op(Operator::Factorial),
+            Token::Op(Operator::Factorial),
+            Token::End
+        ];
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, valid_tokens);
+    }
+
+    #[test]
+    fn valid_percent() {
+        let string = "15% of x";
+        let chars = string.chars();
+        let valid_tokens = vec![
+            Token::Num(15),
+            Token::Op(Operator::Percent),
+            Token::Ident(String::from("of")),
+            Token::Ident(String::from("x")),
+            Token::End
+        ];
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, valid_tokens);
+    }
+
+    #[test]
+    fn number_before_name() {
+        let string = "2x + 1.5kg";
+        let chars = string.chars();
+        let valid_tokens = vec![
+            Token::Num(2),
+            Token::Ident(String::from("x")),
+            Token::Op(Operator::Plus),
+            Token::Decimal(String::from("1.5")),
+            Token::Ident(String::from("kg")),
+            Token::End
+        ];
+        let mut tokenizer = Tokens::new(chars);
+        tokenizer.tokenize();
+        assert_eq!(tokenizer.tokens, valid_tokens);
+        let spans: Vec<(usize, usize)> = tokenizer.spans.iter().map(|s| (s.start, s.end)).collect();
+        assert_eq!(spans, vec![(0, 1), (1, 2), (3, 4), (5, 8), (8, 10), (10, 10)]);
+    }
 }
\ No newline at end of file
@@ -1,32 +1,50 @@
-use std::io::{self, BufRead};
-use std::collections::HashMap;
 mod lexer;
 mod parser;
 mod evaluator;
+mod session;
+mod input;
+mod diagnostic;
+mod highlight;
+mod value;
+mod format;
+mod units;
+mod functions;
+mod matrix;
+mod stats;
+mod symbolic;
+mod solver;
+mod calculus;
+mod constants;
+mod integers;
+mod decimal;
 
 fn main() {
-    let mut symbol_table = HashMap::new();
+    let mut session = session::Session::new();
     println!("Welcome to Rustkalkulator!");
     println!("Press 'q' to quit");
-    loop {
-        let stdin = io::stdin();
-        for line in stdin.lock().lines() {
-            let string = line.unwrap();
-            if string == "q" {
-                println!("Goodbye!");
-                return;
-            }
-            let chars = string.chars();
-            let mut tokenizer = lexer::Tokens::new(chars);
-            tokenizer.tokenize();
-            let mut parser = parser::Parser::new();
-            parser.parse(tokenizer.tokens);
-            let (answer, symbol_update) = evaluator::evaluate(parser.tree, symbol_table);
-            match answer {
+    let mut reader = input::Reader::new();
+    let color = input::color();
+    while let Some(string) = reader.read_line(&session) {
+        if string == "q" {
+            break;
+        }
+        if string.starts_with(':') {
+            match session.command(&string) {
                 Ok(a) => println!("{}", a),
                 Err(e) => println!("{}", e)
             };
-            symbol_table = symbol_update;
+        } else {
+            match session.enter(&string) {
+                Ok(a) => println!("{}", a),
+                Err(e) => println!("{}", e.render(&string, color))
+            };
         }
     }
+    if let Some(Err(e)) = session.finish() {
+        println!("{}", e);
+    }
+    if let Err(e) = reader.finish() {
+        println!("{}", e);
+    }
+    println!("Goodbye!");
 }
@@ -0,0 +1,249 @@
+use super::value::Value;
+use std::convert::TryFrom;
+
+// A matrix is a list of rows of equal length, and a vector is a list of
+// numbers. Both are plain `Value::List`s, so the arithmetic in `value`
+// handles their elementwise operations.
+
+fn numbers(items: &[Value]) -> bool {
+    items.iter().all(|x| !matches!(x, Value::List(_)))
+}
+
+fn rows(value: &Value) -> Option<Vec<Vec<Value>>> {
+    let rows = match value {
+        Value::List(rows) => rows,
+        _ => return None
+    };
+    let rows: Vec<Vec<Value>> = rows.iter().map(|row| match row {
+        Value::List(items) if numbers(items) => Some(items.clone()),
+        _ => None
+    }).collect::<Option<_>>()?;
+    match rows.first() {
+        Some(first) if rows.iter().any(|row| row.len() != first.len()) => None,
+        _ => Some(rows)
+    }
+}
+
+fn vector(value: &Value) -> Option<Vec<Value>> {
+    match value {
+        Value::List(items) if numbers(items) => Some(items.clone()),
+        _ => None
+    }
+}
+
+fn matrix(rows: Vec<Vec<Value>>) -> Value {
+    Value::List(rows.into_iter().map(Value::List).collect())
+}
+
+fn size(rows: &[Vec<Value>]) -> String {
+    format!("{}x{}", rows.len(), rows.first().map_or(0, |row| row.len()))
+}
+
+fn dot(a: &[Value], b: &[Value]) -> Result<Value, String> {
+    if a.len() != b.len() {
+        return Err(format!("Vectors have different lengths: {} and {}", a.len(), b.len()));
+    }
+    let mut sum = Value::Int(0);
+    for (x, y) in a.iter().zip(b) {
+        sum = sum.add(x.clone().mul(y.clone())?)?;
+    }
+    Ok(sum)
+}
+
+fn column(rows: &[Vec<Value>], j: usize) -> Vec<Value> {
+    rows.iter().map(|row| row[j].clone()).collect()
+}
+
+// Matrix products, where a vector stands for a column on the right and
+// for a row on the left, and two vectors give their dot product.
+pub fn matmul(a: Value, b: Value) -> Result<Value, String> {
+    match (vector(&a), vector(&b), rows(&a), rows(&b)) {
+        (Some(u), Some(v), _, _) => dot(&u, &v),
+        (_, Some(v), Some(m), _) => {
+            m.iter().map(|row| dot(row, &v)).collect::<Result<_, _>>().map(Value::List)
+        },
+        (Some(u), _, _, Some(m)) => {
+            if u.len() != m.len() {
+                return Err(format!("Cannot multiply a vector of length {} by a {} matrix", u.len(), size(&m)));
+            }
+            let columns = m.first().map_or(0, |row| row.len());
+            (0..columns).map(|j| dot(&u, &column(&m, j))).collect::<Result<_, _>>().map(Value::List)
+        },
+        (_, _, Some(p), Some(q)) => {
+            let inner = p.first().map_or(0, |row| row.len());
+            if inner != q.len() {
+                return Err(format!("Cannot multiply a {} matrix by a {} matrix", size(&p), size(&q)));
+            }
+            let columns = q.first().map_or(0, |row| row.len());
+            let product = p.iter()
+                .map(|row| (0..columns).map(|j| dot(row, &column(&q, j))).collect::<Result<_, _>>())
+                .collect::<Result<_, _>>()?;
+            Ok(matrix(product))
+        },
+        _ => Err(String::from("@ multiplies vectors and matrices; use * for numbers"))
+    }
+}
+
+pub fn transpose(value: Value) -> Result<Value, String> {
+    if let Some(items) = vector(&value) {
+        return Ok(matrix(items.into_iter().map(|x| vec![x]).collect()));
+    }
+    let m = rows(&value).ok_or_else(not_a_matrix)?;
+    let columns = m.first().map_or(0, |row| row.len());
+    Ok(matrix((0..columns).map(|j| column(&m, j)).collect()))
+}
+
+// Fraction-free elimination (Bareiss), so that integer matrices have an
+// exact integer determinant.
+pub fn det(value: Value) -> Result<Value, String> {
+    let mut m = square(&value, "A determinant")?;
+    let n = m.len();
+    let mut sign = Value::Int(1);
+    let mut previous = Value::Int(1);
+    for k in 0..n {
+        if m[k][k].is_zero() {
+            match (k + 1..n).find(|i| !m[*i][k].is_zero()) {
+                Some(i) => {
+                    m.swap(i, k);
+                    sign = sign.neg()?;
+                },
+                None => return Ok(Value::Int(0))
+            }
+        }
+        for i in k + 1..n {
+            for j in k + 1..n {
+                let cross = m[i][j].clone().mul(m[k][k].clone())?.sub(m[i][k].clone().mul(m[k][j].clone())?)?;
+                m[i][j] = cross.div(previous.clone())?;
+            }
+        }
+        previous = m[k][k].clone();
+    }
+    match n {
+        0 => Ok(Value::Int(1)),
+        _ => sign.mul(m[n - 1][n - 1].clone())
+    }
+}
+
+// Gauss-Jordan elimination with partial pivoting, in real arithmetic so
+// that integer matrices do not divide down to zero.
+pub fn inverse(value: Value) -> Result<Value, String> {
+    let m = square(&value, "Only a square matrix")?;
+    let n = m.len();
+    let real = |x: &Value| match x {
+        Value::Int(x) => Value::Real(*x as f64),
+        x => x.clone()
+    };
+    let mut a: Vec<Vec<Value>> = m.iter().enumerate().map(|(i, row)| {
+        let identity = (0..n).map(|j| Value::Real(if i == j { 1.0 } else { 0.0 }));
+        row.iter().map(real).chain(identity).collect()
+    }).collect();
+    for k in 0..n {
+        let size = |x: &Value| x.clone().abs().map_or(0.0, |x| x.to_f64());
+        let pivot = (k..n).max_by(|i, j| size(&a[*i][k]).total_cmp(&size(&a[*j][k]))).unwrap();
+        if a[pivot][k].is_zero() {
+            return Err(String::from("Matrix is singular"));
+        }
+        a.swap(pivot, k);
+        let lead = a[k][k].clone();
+        a[k] = a[k].iter().map(|x| x.clone().div(lead.clone())).collect::<Result<_, _>>()?;
+        let pivot_row = a[k].clone();
+        for row in a.iter_mut().enumerate().filter(|(i, _)| *i != k).map(|(_, row)| row) {
+            let factor = row[k].clone();
+            for (x, p) in row.iter_mut().zip(&pivot_row) {
+                *x = x.clone().sub(factor.clone().mul(p.clone())?)?;
+            }
+        }
+    }
+    Ok(matrix(a.into_iter().map(|row| row[n..].to_vec()).collect()))
+}
+
+pub fn index(value: Value, index: Value) -> Result<Value, String> {
+    let items = match value {
+        Value::List(items) => items,
+        _ => return Err(String::from("Only lists can be indexed"))
+    };
+    let i = match index {
+        Value::Int(i) => i,
+        _ => return Err(String::from("Indices must be integers"))
+    };
+    match usize::try_from(i).ok().and_then(|i| items.get(i)) {
+        Some(x) => Ok(x.clone()),
+        None => Err(format!("Index {} out of range for a list of length {}", i, items.len()))
+    }
+}
+
+fn square(value: &Value, what: &str) -> Result<Vec<Vec<Value>>, String> {
+    let m = rows(value).ok_or_else(not_a_matrix)?;
+    if m.iter().any(|row| row.len() != m.len()) {
+        return Err(format!("{} needs a square matrix, got {}", what, size(&m)));
+    }
+    Ok(m)
+}
+
+fn not_a_matrix() -> String {
+    String::from("Expected a matrix")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ints(rows: &[&[i128]]) -> Value {
+        matrix(rows.iter().map(|row| row.iter().map(|x| Value::Int(*x)).collect()).collect())
+    }
+
+    fn vector_of(items: &[i128]) -> Value {
+        Value::List(items.iter().map(|x| Value::Int(*x)).collect())
+    }
+
+    #[test]
+    fn products() {
+        let m = ints(&[&[1, 2], &[3, 4]]);
+        assert_eq!(matmul(m.clone(), m.clone()), Ok(ints(&[&[7, 10], &[15, 22]])));
+        assert_eq!(matmul(m.clone(), vector_of(&[1, 1])), Ok(vector_of(&[3, 7])));
+        assert_eq!(matmul(vector_of(&[1, 1]), m.clone()), Ok(vector_of(&[4, 6])));
+        assert_eq!(matmul(vector_of(&[1, 2, 3]), vector_of(&[4, 5, 6])), Ok(Value::Int(32)));
+        let wide = ints(&[&[1, 2, 3], &[4, 5, 6]]);
+        assert_eq!(matmul(wide.clone(), wide), Err(String::from("Cannot multiply a 2x3 matrix by a 2x3 matrix")));
+        assert!(matmul(Value::Int(2), m).is_err());
+    }
+
+    #[test]
+    fn transposes() {
+        assert_eq!(transpose(ints(&[&[1, 2, 3], &[4, 5, 6]])), Ok(ints(&[&[1, 4], &[2, 5], &[3, 6]])));
+        assert_eq!(transpose(vector_of(&[1, 2])), Ok(ints(&[&[1], &[2]])));
+        assert!(transpose(Value::List(vec![vector_of(&[1]), vector_of(&[1, 2])])).is_err());
+    }
+
+    #[test]
+    fn determinants() {
+        assert_eq!(det(ints(&[&[1, 2], &[3, 4]])), Ok(Value::Int(-2)));
+        assert_eq!(det(ints(&[&[0, 1, 2], &[1, 0, 3], &[4, -3, 8]])), Ok(Value::Int(-2)));
+        assert_eq!(det(ints(&[&[1, 2], &[2, 4]])), Ok(Value::Int(0)));
+        assert_eq!(det(ints(&[&[1, 2, 3]])), Err(String::from("A determinant needs a square matrix, got 1x3")));
+    }
+
+    #[test]
+    fn inverses() {
+        let expected = [[0.6, -0.7], [-0.2, 0.4]];
+        let rows = rows(&inverse(ints(&[&[4, 7], &[2, 6]])).unwrap()).unwrap();
+        for (row, expected) in rows.iter().zip(expected.iter()) {
+            for (x, y) in row.iter().zip(expected.iter()) {
+                assert!((x.to_f64() - y).abs() < 1e-12);
+            }
+        }
+        assert_eq!(inverse(ints(&[&[2, 0], &[0, 4]])), Ok(matrix(vec![
+            vec![Value::Real(0.5), Value::Real(0.0)],
+            vec![Value::Real(0.0), Value::Real(0.25)]
+        ])));
+        assert_eq!(super::inverse(ints(&[&[1, 2], &[2, 4]])), Err(String::from("Matrix is singular")));
+    }
+
+    #[test]
+    fn indexing() {
+        let m = ints(&[&[1, 2], &[3, 4]]);
+        assert_eq!(index(m.clone(), Value::Int(1)).and_then(|row| index(row, Value::Int(0))), Ok(Value::Int(3)));
+        assert_eq!(index(m, Value::Int(2)), Err(String::from("Index 2 out of range for a list of length 2")));
+        assert!(index(Value::Int(5), Value::Int(0)).is_err());
+    }
+}
@@ -1,73 +1,265 @@
 use super::lexer::Token;
 use super::lexer::Operator;
+use std::fmt;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum ParseTree {
     Sum(SumOp, Box<ParseTree>, Box<ParseTree>),
     Prod(ProdOp, Box<ParseTree>, Box<ParseTree>),
     Assign(String, Box<ParseTree>),
+    Define(String, Box<ParseTree>),
+    Const(String, Box<ParseTree>),
+    Bit(BitOp, Box<ParseTree>, Box<ParseTree>),
+    Power(Box<ParseTree>, Box<ParseTree>),
     Unary(SumOp, Box<ParseTree>),
-    Num(i32),
+    Not(Box<ParseTree>),
+    Convert(Box<ParseTree>, Box<ParseTree>),
+    Call(String, Vec<ParseTree>),
+    List(Vec<ParseTree>),
+    Index(Box<ParseTree>, Box<ParseTree>),
+    Factorial(Box<ParseTree>),
+    Percent(Box<ParseTree>),
+    Range(Box<ParseTree>, Box<ParseTree>),
+    Equation(Box<ParseTree>, Box<ParseTree>),
+    Num(i128),
+    Real(f64),
+    // A literal with a fraction or an exponent, as the user wrote it.
+    Decimal(String),
+    Imag(f64),
     Var(String)
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum SumOp {
     Plus,
     Minus
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum ProdOp {
     Times,
-    Divide
+    Divide,
+    MatMul
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum BitOp {
+    And,
+    Or,
+    Xor,
+    ShiftLeft,
+    ShiftRight
+}
+
+// Binary operators from the loosest to the tightest binding.
+const PRECEDENCE: &[&[Operator]] = &[
+    &[Operator::Or],
+    &[Operator::Xor],
+    &[Operator::And],
+    &[Operator::ShiftLeft, Operator::ShiftRight],
+    &[Operator::Range],
+    &[Operator::Plus, Operator::Minus],
+    &[Operator::Times, Operator::Divide, Operator::MatMul],
+];
+
+fn node(op: Operator, left: Box<ParseTree>, right: Box<ParseTree>) -> ParseTree {
+    match op {
+        Operator::Plus => ParseTree::Sum(SumOp::Plus, left, right),
+        Operator::Minus => ParseTree::Sum(SumOp::Minus, left, right),
+        Operator::Times => ParseTree::Prod(ProdOp::Times, left, right),
+        Operator::Divide => ParseTree::Prod(ProdOp::Divide, left, right),
+        Operator::MatMul => ParseTree::Prod(ProdOp::MatMul, left, right),
+        Operator::And => ParseTree::Bit(BitOp::And, left, right),
+        Operator::Or => ParseTree::Bit(BitOp::Or, left, right),
+        Operator::Xor => ParseTree::Bit(BitOp::Xor, left, right),
+        Operator::ShiftLeft => ParseTree::Bit(BitOp::ShiftLeft, left, right),
+        Operator::ShiftRight => ParseTree::Bit(BitOp::ShiftRight, left, right),
+        Operator::Range => ParseTree::Range(left, right),
+        Operator::Not | Operator::Power | Operator::Factorial | Operator::Percent => unreachable!("~, ^, ! and % are parsed with the factors")
+    }
+}
+
+impl ParseTree {
+    fn binary(&self) -> Option<(Operator, &ParseTree, &ParseTree)> {
+        let op = match self {
+            ParseTree::Sum(SumOp::Plus, ..) => Operator::Plus,
+            ParseTree::Sum(SumOp::Minus, ..) => Operator::Minus,
+            ParseTree::Prod(ProdOp::Times, ..) => Operator::Times,
+            ParseTree::Prod(ProdOp::Divide, ..) => Operator::Divide,
+            ParseTree::Prod(ProdOp::MatMul, ..) => Operator::MatMul,
+            ParseTree::Bit(BitOp::And, ..) => Operator::And,
+            ParseTree::Bit(BitOp::Or, ..) => Operator::Or,
+            ParseTree::Bit(BitOp::Xor, ..) => Operator::Xor,
+            ParseTree::Bit(BitOp::ShiftLeft, ..) => Operator::ShiftLeft,
+            ParseTree::Bit(BitOp::ShiftRight, ..) => Operator::ShiftRight,
+            ParseTree::Range(..) => Operator::Range,
+            _ => return None
+        };
+        match self {
+            ParseTree::Sum(_, a, b) | ParseTree::Prod(_, a, b) | ParseTree::Bit(_, a, b) | ParseTree::Range(a, b) => Some((op, a, b)),
+            _ => None
+        }
+    }
+
+    // How tightly the top of the tree binds, counted the same way as
+    // `PRECEDENCE` with room for assignment and conversion below it.
+    fn binding(&self) -> usize {
+        if let Some((op, _, _)) = self.binary() {
+            return 2 + PRECEDENCE.iter().position(|level| level.contains(&op)).unwrap();
+        }
+        match self {
+            ParseTree::Assign(..) | ParseTree::Define(..) | ParseTree::Const(..) | ParseTree::Equation(..) => 0,
+            ParseTree::Convert(..) => 1,
+            ParseTree::Unary(..) | ParseTree::Not(_) => 2 + PRECEDENCE.len(),
+            ParseTree::Power(..) => 3 + PRECEDENCE.len(),
+            _ => 4 + PRECEDENCE.len()
+        }
+    }
+}
+
+fn symbol(op: Operator) -> &'static str {
+    match op {
+        Operator::Plus => " + ",
+        Operator::Minus => " - ",
+        Operator::Times => "*",
+        Operator::Divide => "/",
+        Operator::MatMul => " @ ",
+        Operator::Power => "^",
+        Operator::And => " & ",
+        Operator::Or => " | ",
+        Operator::Xor => " xor ",
+        Operator::Not => "~",
+        Operator::Factorial => "!",
+        Operator::Percent => "%",
+        Operator::ShiftLeft => " << ",
+        Operator::ShiftRight => " >> ",
+        Operator::Range => ".."
+    }
+}
+
+// Infix text that parses back into the same tree, with only the
+// parentheses the precedence rules need.
+impl fmt::Display for ParseTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let grouped = |tree: &ParseTree, parens: bool| if parens { format!("({})", tree) } else { tree.to_string() };
+        let binding = self.binding();
+        if let Some((op, a, b)) = self.binary() {
+            return write!(f, "{}{}{}", grouped(a, a.binding() < binding), symbol(op), grouped(b, b.binding() <= binding));
+        }
+        let list = |items: &[ParseTree]| items.iter().map(|item| item.to_string()).collect::<Vec<_>>().join(", ");
+        match self {
+            ParseTree::Assign(name, a) => write!(f, "{} = {}", name, a),
+            ParseTree::Define(name, a) => write!(f, "{} := {}", name, a),
+            ParseTree::Const(name, a) => write!(f, "const {} = {}", name, a),
+            ParseTree::Equation(a, b) => write!(f, "{} = {}", a, b),
+            ParseTree::Convert(a, b) => write!(f, "{} to {}", grouped(a, a.binding() <= binding), b),
+            // The exponent is read with its own sign, so `x^-2` is fine.
+            ParseTree::Power(a, b) => write!(f, "{}^{}", grouped(a, a.binding() <= binding), grouped(b, b.binding() + 1 < binding)),
+            // A minus sign can move out of a product, so `-2*x` needs no parentheses.
+            ParseTree::Unary(op, a) => {
+                let sign = if *op == SumOp::Minus { "-" } else { "+" };
+                write!(f, "{}{}", sign, grouped(a, a.binding() + 1 < binding))
+            },
+            ParseTree::Not(a) => write!(f, "~{}", grouped(a, a.binding() < binding)),
+            ParseTree::Call(name, arguments) => write!(f, "{}({})", name, list(arguments)),
+            ParseTree::List(items) => write!(f, "[{}]", list(items)),
+            ParseTree::Index(a, i) => write!(f, "{}[{}]", grouped(a, a.binding() < binding), i),
+            ParseTree::Factorial(a) => write!(f, "{}!", grouped(a, a.binding() < binding)),
+            ParseTree::Percent(a) => write!(f, "{}%", grouped(a, a.binding() < binding)),
+            ParseTree::Num(x) => write!(f, "{}", x),
+            ParseTree::Real(x) => write!(f, "{:?}", x),
+            ParseTree::Decimal(text) => write!(f, "{}", text),
+            ParseTree::Imag(x) => write!(f, "{:?}i", x),
+            ParseTree::Var(name) => write!(f, "{}", name),
+            _ => unreachable!("binary operators are written above")
+        }
+    }
+}
+
+fn missing(close: &Token) -> String {
+    match close {
+        Token::RBracket => String::from("Missing right bracket"),
+        _ => String::from("Missing right parenthesis")
+    }
+}
+
+fn multiplies(token: &Token) -> bool {
+    match token {
+        Token::Ident(name) => name != "to",
+        Token::LParen => true,
+        _ => false
+    }
 }
 
 #[derive(Debug)]
 pub struct Parser {
     pub tree: Result<Box<ParseTree>, String>,
+    pub error_at: Option<usize>,
+    pub incomplete: bool,
 }
 
 impl Parser {
     pub fn new() -> Parser {
         Parser { 
             tree: Err(String::from("Nothing to parse")),
+            error_at: None,
+            incomplete: false,
         }
     }
 
     pub fn parse(&mut self, tokens: Vec<Token>) {
-        self.tree = match Parser::expression(tokens.into_iter(), None) {
-            (tree, _, _) => tree
-        }   
+        let total = tokens.len();
+        let ends_input = tokens.last() == Some(&Token::End);
+        let (mut tree, mut rest, mut token) = Parser::expression(tokens.into_iter(), None);
+        if tree.is_ok() && token == Some(Token::Ident(String::from("to"))) {
+            let (target, target_rest, target_token) = Parser::expression(rest, None);
+            tree = match (tree, target) {
+                (Ok(tree), Ok(target)) => Ok(Box::new(ParseTree::Convert(tree, target))),
+                (_, Err(e)) | (Err(e), _) => Err(e)
+            };
+            rest = target_rest;
+            token = target_token;
+        }
+        // Every error is returned right after its offending token was taken,
+        // so the number of consumed tokens points just past it.
+        let consumed = total - rest.len();
+        self.tree = match (tree, token) {
+            (Ok(tree), None) | (Ok(tree), Some(Token::End)) => Ok(tree),
+            (Ok(_), Some(_)) => Err(String::from("Unexpected input")),
+            (Err(e), _) => Err(e)
+        };
+        self.error_at = match self.tree {
+            Ok(_) => None,
+            Err(_) => Some(consumed.saturating_sub(1))
+        };
+        // Running into the end of input means more lines could still fix it.
+        self.incomplete = ends_input && self.error_at == Some(total - 1);
     }
 
-    fn expression(tokens: std::vec::IntoIter<Token>, token: Option<Token>) -> (Result<Box<ParseTree>, String>, std::vec::IntoIter<Token>, Option<Token>) {
-        let (term_result, mut tokens, mut token) = Parser::term(tokens, token);
-        if token == None {
-            token = tokens.next();
+    fn expression(mut tokens: std::vec::IntoIter<Token>, token: Option<Token>) -> (Result<Box<ParseTree>, String>, std::vec::IntoIter<Token>, Option<Token>) {
+        // `const x = 5` declares a value that cannot change. Anywhere else
+        // `const` is just a name.
+        let token = token.or_else(|| tokens.next());
+        if let (Some(Token::Ident(keyword)), [Token::Ident(name), Token::Assign, ..]) = (&token, tokens.as_slice()) {
+            if keyword == "const" && !name.starts_with('$') {
+                let name = name.clone();
+                tokens.nth(1);
+                return match Parser::expression(tokens, None) {
+                    (Err(e), tokens, _) => (Err(e), tokens, None),
+                    (Ok(tree), tokens, token) => (Ok(Box::new(ParseTree::Const(name, tree))), tokens, token)
+                };
+            }
         }
-        match term_result { 
-            Err(_) => (term_result, tokens, None),
-            Ok(term_tree) => {
+        let (binary_result, tokens, token) = Parser::binary(0, tokens, token);
+        match binary_result {
+            Err(_) => (binary_result, tokens, None),
+            Ok(binary_tree) => {
                 match token {
-                    Some(Token::Op(op)) => {
-                        match op {
-                            Operator::Plus | Operator::Minus => {
-                                let node_op = if op == Operator::Plus { SumOp::Plus } else { SumOp::Minus };
-                                match Parser::expression(tokens, None) { 
-                                    (Err(e), tokens, _) => (Err(e), tokens, None), 
-                                    (Ok(expression_tree), tokens, token) => {
-                                        (Ok(Box::new(ParseTree::Sum(node_op, term_tree, expression_tree))), tokens, token)
-                                    }
-                                }
-                            },
-                            _ => {
-                                (Ok(term_tree), tokens, token)
-                            }
-                        }
-                    },
                     Some(Token::Assign) => {
-                        match *term_tree {
+                        match *binary_tree {
+                            ParseTree::Var(s) if s.starts_with('$') => {
+                                (Err(format!("Output {} cannot be assigned to", s)), tokens, None)
+                            },
                             ParseTree::Var(s) => {
                                 match Parser::expression(tokens, None) {
                                     (Err(e), tokens, _) => (Err(e), tokens, None),
@@ -79,75 +271,228 @@ impl Parser {
                             _ => (Err(String::from("Only variables can be assigned to")), tokens, None)
                         }
                     },
-                    Some(Token::Error(s)) => (Err(format!("Unexpected end of input: {}", s)), tokens, None),
-                    _ => {
-                        (Ok(term_tree), tokens, token)
+                    // A definition is a single formula, so it does not chain
+                    // like assignments do.
+                    Some(Token::Define) => {
+                        match *binary_tree {
+                            ParseTree::Var(s) if !s.starts_with('$') => {
+                                match Parser::binary(0, tokens, None) {
+                                    (Err(e), tokens, _) => (Err(e), tokens, None),
+                                    (Ok(expression_tree), tokens, token) => {
+                                        (Ok(Box::new(ParseTree::Define(s, expression_tree))), tokens, token)
+                                    }
+                                }
+                            },
+                            _ => (Err(String::from("Only variables can be defined")), tokens, None)
+                        }
+                    },
+                    _ => (Ok(binary_tree), tokens, token)
+                }
+            }
+        }
+    }
+
+    // Binary operators are parsed one precedence level at a time, from the
+    // loosest binding level in `PRECEDENCE` down to single factors.
+    fn binary(level: usize, tokens: std::vec::IntoIter<Token>, token: Option<Token>) -> (Result<Box<ParseTree>, String>, std::vec::IntoIter<Token>, Option<Token>) {
+        let operand = |tokens, token| if level + 1 < PRECEDENCE.len() {
+            Parser::binary(level + 1, tokens, token)
+        } else {
+            Parser::juxtaposed(tokens, token)
+        };
+        let (mut result, mut tokens, mut token) = operand(tokens, token);
+        loop {
+            let left = match result {
+                Err(_) => return (result, tokens, None),
+                Ok(left) => left
+            };
+            if token.is_none() {
+                token = tokens.next();
+            }
+            match token {
+                Some(Token::Op(op)) if PRECEDENCE[level].contains(&op) => {
+                    let (right, rest, next) = operand(tokens, None);
+                    result = right.map(|right| Box::new(node(op, left, right)));
+                    tokens = rest;
+                    token = next;
+                },
+                Some(Token::Error(s)) => return (Err(format!("Parse error on token: {}", s)), tokens, None),
+                _ => return (Ok(left), tokens, token)
+            }
+        }
+    }
+
+    // A name or parenthesis right after a factor multiplies it, as in `3 m`,
+    // and binds tighter than `*` and `/` so that `2 h / 30 min` is 4.
+    fn juxtaposed(tokens: std::vec::IntoIter<Token>, token: Option<Token>) -> (Result<Box<ParseTree>, String>, std::vec::IntoIter<Token>, Option<Token>) {
+        let (mut result, mut tokens, mut token) = Parser::power(tokens, token);
+        loop {
+            let left = match result {
+                Err(_) => return (result, tokens, None),
+                Ok(left) => left
+            };
+            if token.is_none() {
+                token = tokens.next();
+            }
+            match token {
+                Some(ref next) if multiplies(next) => {
+                    let (right, rest, next) = Parser::power(tokens, token);
+                    result = right.map(|right| Box::new(ParseTree::Prod(ProdOp::Times, left, right)));
+                    tokens = rest;
+                    token = next;
+                },
+                _ => return (Ok(left), tokens, token)
+            }
+        }
+    }
+
+    // The comma separated arguments of a call or items of a list, up to and
+    // including the closing parenthesis or bracket.
+    fn arguments(mut tokens: std::vec::IntoIter<Token>, close: Token) -> (Result<Vec<ParseTree>, String>, std::vec::IntoIter<Token>) {
+        let mut arguments = Vec::new();
+        let mut token = tokens.next();
+        if token.as_ref() == Some(&close) {
+            return (Ok(arguments), tokens);
+        }
+        loop {
+            match Parser::equation(tokens, token) {
+                (Err(e), rest, _) => return (Err(e), rest),
+                (Ok(argument), rest, next) => {
+                    arguments.push(*argument);
+                    tokens = rest;
+                    match next {
+                        Some(Token::Comma) => token = None,
+                        Some(next) if next == close => return (Ok(arguments), tokens),
+                        _ => return (Err(missing(&close)), tokens)
                     }
                 }
             }
         }
     }
 
-    fn term(tokens: std::vec::IntoIter<Token>, token: Option<Token>) -> (Result<Box<ParseTree>, String>, std::vec::IntoIter<Token>, Option<Token>) {
-        let (factor_result, mut tokens, mut token) = Parser::factor(tokens, token);
-        if token == None {
+    // Inside brackets `=` states an equation for `solve` rather than
+    // assigning to a variable.
+    fn equation(tokens: std::vec::IntoIter<Token>, token: Option<Token>) -> (Result<Box<ParseTree>, String>, std::vec::IntoIter<Token>, Option<Token>) {
+        match Parser::binary(0, tokens, token) {
+            (Ok(left), tokens, Some(Token::Assign)) => match Parser::binary(0, tokens, None) {
+                (Ok(right), tokens, token) => (Ok(Box::new(ParseTree::Equation(left, right))), tokens, token),
+                (Err(e), tokens, _) => (Err(e), tokens, None)
+            },
+            (result, tokens, token) => (result, tokens, token)
+        }
+    }
+
+    // `^` binds tighter than a leading sign and groups to the right, so
+    // `-2^2` is -4 and `2^3^2` is 2^9.
+    fn power(tokens: std::vec::IntoIter<Token>, token: Option<Token>) -> (Result<Box<ParseTree>, String>, std::vec::IntoIter<Token>, Option<Token>) {
+        let (base, mut tokens, mut token) = Parser::factor(tokens, token);
+        let mut base = match base {
+            Err(e) => return (Err(e), tokens, None),
+            Ok(base) => base
+        };
+        if token.is_none() {
             token = tokens.next();
         }
-        match factor_result {
-            Err(_) => (factor_result, tokens, None),
-            Ok(factor_tree) => {
-                match token {
-                    Some(Token::Op(op)) => {
-                        match op {
-                            Operator::Times | Operator::Divide => {
-                                let tree_op = if op == Operator::Times { ProdOp::Times } else { ProdOp::Divide };
-                                match Parser::term(tokens, None) {
-                                    (Err(e), tokens, _) => (Err(e), tokens, None),
-                                    (Ok(term_tree), tokens, token) => {
-                                        (Ok(Box::new(ParseTree::Prod(tree_op, factor_tree, term_tree))), tokens, token)
-                                    }
-                                }
-                            },
-                            _ => (Ok(factor_tree), tokens, token)
-                        }
+        // Indexing and factorials bind tightest of all, as in `m[0][1]^2`
+        // and `n!^2`.
+        loop {
+            match token {
+                Some(Token::LBracket) => match Parser::expression(tokens, None) {
+                    (Ok(index), rest, Some(Token::RBracket)) => {
+                        base = Box::new(ParseTree::Index(base, index));
+                        tokens = rest;
+                        token = tokens.next();
                     },
-                    Some(Token::Error(s)) => (Err(format!("Parse error on token: {}", s)), tokens, None),
-                    _ => (Ok(factor_tree), tokens, token)
-                }
+                    (Err(e), rest, _) => return (Err(e), rest, None),
+                    (_, rest, _) => return (Err(missing(&Token::RBracket)), rest, None)
+                },
+                Some(Token::Op(Operator::Factorial)) => {
+                    base = Box::new(ParseTree::Factorial(base));
+                    token = tokens.next();
+                },
+                // `15% of y` is 15% times y, and `x as % of y` is x over y%.
+                Some(Token::Op(Operator::Percent)) => {
+                    base = Box::new(ParseTree::Percent(base));
+                    token = tokens.next();
+                    if token == Some(Token::Ident(String::from("of"))) {
+                        return match Parser::power(tokens, None) {
+                            (Ok(whole), tokens, token) => (Ok(Box::new(ParseTree::Prod(ProdOp::Times, base, whole))), tokens, token),
+                            (Err(e), tokens, _) => (Err(e), tokens, None)
+                        };
+                    }
+                },
+                Some(Token::Ident(ref word)) if word == "as" && tokens.as_slice().starts_with(&[Token::Op(Operator::Percent), Token::Ident(String::from("of"))]) => {
+                    tokens.nth(1);
+                    return match Parser::power(tokens, None) {
+                        (Ok(whole), tokens, token) => (Ok(Box::new(ParseTree::Prod(ProdOp::Divide, base, Box::new(ParseTree::Percent(whole))))), tokens, token),
+                        (Err(e), tokens, _) => (Err(e), tokens, None)
+                    };
+                },
+                _ => break
             }
         }
+        match token {
+            Some(Token::Op(Operator::Power)) => match Parser::power(tokens, None) {
+                (Err(e), tokens, _) => (Err(e), tokens, None),
+                (Ok(exponent), tokens, token) => (Ok(Box::new(ParseTree::Power(base, exponent))), tokens, token)
+            },
+            _ => (Ok(base), tokens, token)
+        }
     }
 
     fn factor(mut tokens: std::vec::IntoIter<Token>, mut token: Option<Token>) -> (Result<Box<ParseTree>, String>, std::vec::IntoIter<Token>, Option<Token>) {
-        if token == None {
+        if token.is_none() {
             token = tokens.next();
         }
         match token {
             Some(Token::Num(n)) => {
                 (Ok(Box::new(ParseTree::Num(n))), tokens, None)
             },
-            Some(Token::Ident(i)) => {
-                (Ok(Box::new(ParseTree::Var(i))), tokens, None)
+            Some(Token::Decimal(text)) => {
+                (Ok(Box::new(ParseTree::Decimal(text))), tokens, None)
+            },
+            Some(Token::Imag(x)) => {
+                (Ok(Box::new(ParseTree::Imag(x))), tokens, None)
+            },
+            // A name directly followed by a parenthesis is a function call.
+            Some(Token::Ident(i)) => match tokens.next() {
+                Some(Token::LParen) => match Parser::arguments(tokens, Token::RParen) {
+                    (Ok(arguments), tokens) => (Ok(Box::new(ParseTree::Call(i, arguments))), tokens, None),
+                    (Err(e), tokens) => (Err(e), tokens, None)
+                },
+                next => (Ok(Box::new(ParseTree::Var(i))), tokens, next)
             },
             Some(Token::Op(op)) => {
                 match op {
                     Operator::Plus | Operator::Minus => {
                         let tree_op = if op == Operator::Plus { SumOp::Plus } else { SumOp::Minus };
-                        match Parser::factor(tokens, None) {
+                        match Parser::power(tokens, None) {
                             (Err(e), tokens, _) => (Err(e), tokens, None),
                             (Ok(factor_tree), tokens, token) => (Ok(Box::new(ParseTree::Unary(tree_op, factor_tree))), tokens, token)
                         }
                     },
+                    Operator::Not => {
+                        match Parser::power(tokens, None) {
+                            (Err(e), tokens, _) => (Err(e), tokens, None),
+                            (Ok(factor_tree), tokens, token) => (Ok(Box::new(ParseTree::Not(factor_tree))), tokens, token)
+                        }
+                    },
                     _ => (Err(String::from("Invalid unary operator")), tokens, None)
                 }
             },
+            Some(Token::LBracket) => match Parser::arguments(tokens, Token::RBracket) {
+                (Ok(items), tokens) => (Ok(Box::new(ParseTree::List(items))), tokens, None),
+                (Err(e), tokens) => (Err(e), tokens, None)
+            },
             Some(Token::LParen) => {
                 match Parser::expression(tokens, None) {
                     (Ok(expression_tree), tokens, Some(Token::RParen)) => (Ok(expression_tree), tokens, None),
-                    (_, tokens, _)=> (Err(String::from("Missing right parenthesis")), tokens, None)
+                    (Err(e), tokens, _) => (Err(e), tokens, None),
+                    (_, tokens, _) => (Err(String::from("Missing right parenthesis")), tokens, None)
                 }
             },
             Some(Token::Error(s)) => (Err(format!("Parse error on token: {}", s)), tokens, None),
+            Some(Token::Overflow(s)) => (Err(format!("Number out of range: {}", s)), tokens, None),
             _ => (Err(String::from("Unexpected end of input")), tokens, None)
         }
     }
@@ -320,4 +665,511 @@ mod test {
         invalid_parser.parse(invalid_tokens);
         assert!(invalid_parser.tree.is_err(), "Unexpected end of input");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn invalid_output_assign() {
+        let invalid_tokens = vec![
+            Token::Ident(String::from("$1")),
+            Token::Assign,
+            Token::Num(3)
+        ];
+        let mut invalid_parser = Parser::new();
+        invalid_parser.parse(invalid_tokens);
+        assert_eq!(invalid_parser.tree, Err(String::from("Output $1 cannot be assigned to")));
+    }
+
+    #[test]
+    fn error_position() {
+        let invalid_tokens = vec![
+            Token::Num(1),
+            Token::Op(Operator::Plus),
+            Token::Op(Operator::Times),
+            Token::Num(2),
+            Token::End
+        ];
+        let mut invalid_parser = Parser::new();
+        invalid_parser.parse(invalid_tokens);
+        assert_eq!(invalid_parser.tree, Err(String::from("Invalid unary operator")));
+        assert_eq!(invalid_parser.error_at, Some(2));
+    }
+
+    #[test]
+    fn missing_paren_position() {
+        let invalid_tokens = vec![
+            Token::LParen,
+            Token::Num(1),
+            Token::End
+        ];
+        let mut invalid_parser = Parser::new();
+        invalid_parser.parse(invalid_tokens);
+        assert_eq!(invalid_parser.tree, Err(String::from("Missing right parenthesis")));
+        assert_eq!(invalid_parser.error_at, Some(2));
+    }
+
+    #[test]
+    fn trailing_input() {
+        let invalid_tokens = vec![
+            Token::Num(1),
+            Token::Num(2),
+            Token::End
+        ];
+        let mut invalid_parser = Parser::new();
+        invalid_parser.parse(invalid_tokens);
+        assert_eq!(invalid_parser.tree, Err(String::from("Unexpected input")));
+        assert_eq!(invalid_parser.error_at, Some(1));
+    }
+
+    #[test]
+    fn incomplete_input() {
+        let incomplete_tokens = vec![
+            Token::LParen,
+            Token::Num(1),
+            Token::Op(Operator::Plus),
+            Token::End
+        ];
+        let mut incomplete_parser = Parser::new();
+        incomplete_parser.parse(incomplete_tokens);
+        assert!(incomplete_parser.tree.is_err());
+        assert!(incomplete_parser.incomplete);
+    }
+
+    #[test]
+    fn invalid_not_incomplete() {
+        let invalid_tokens = vec![
+            Token::Num(1),
+            Token::Op(Operator::Plus),
+            Token::RParen,
+            Token::End
+        ];
+        let mut invalid_parser = Parser::new();
+        invalid_parser.parse(invalid_tokens);
+        assert!(invalid_parser.tree.is_err());
+        assert!(!invalid_parser.incomplete);
+    }
+
+    #[test]
+    fn valid_convert() {
+        let valid_tokens = vec![
+            Token::Decimal(String::from("2.5")),
+            Token::Op(Operator::Times),
+            Token::Ident(String::from("x")),
+            Token::Ident(String::from("to")),
+            Token::Ident(String::from("sci")),
+            Token::End
+        ];
+        let valid_tree = Box::new(ParseTree::Convert(
+            Box::new(ParseTree::Prod(
+                ProdOp::Times,
+                Box::new(ParseTree::Decimal(String::from("2.5"))),
+                Box::new(ParseTree::Var(String::from("x")))
+            )),
+            Box::new(ParseTree::Var(String::from("sci")))
+        ));
+        let mut valid_parser = Parser::new();
+        valid_parser.parse(valid_tokens);
+        assert_eq!(valid_parser.tree.unwrap(), valid_tree);
+    }
+
+    #[test]
+    fn invalid_convert() {
+        let invalid_tokens = vec![
+            Token::Num(1),
+            Token::Ident(String::from("to")),
+            Token::End
+        ];
+        let mut invalid_parser = Parser::new();
+        invalid_parser.parse(invalid_tokens);
+        assert_eq!(invalid_parser.tree, Err(String::from("Unexpected end of input")));
+        assert!(invalid_parser.incomplete);
+    }
+
+    #[test]
+    fn number_overflow() {
+        let invalid_tokens = vec![
+            Token::Num(1),
+            Token::Op(Operator::Plus),
+            Token::Overflow(String::from("0xFFFFFFFF"))
+        ];
+        let mut invalid_parser = Parser::new();
+        invalid_parser.parse(invalid_tokens);
+        assert_eq!(invalid_parser.tree, Err(String::from("Number out of range: 0xFFFFFFFF")));
+        assert_eq!(invalid_parser.error_at, Some(2));
+    }
+
+    #[test]
+    fn left_associative() {
+        let valid_tokens = vec![
+            Token::Num(5),
+            Token::Op(Operator::Minus),
+            Token::Num(2),
+            Token::Op(Operator::Plus),
+            Token::Num(1),
+            Token::End
+        ];
+        let valid_tree = Box::new(ParseTree::Sum(
+            SumOp::Plus,
+            Box::new(ParseTree::Sum(
+                SumOp::Minus,
+                Box::new(ParseTree::Num(5)),
+                Box::new(ParseTree::Num(2))
+            )),
+            Box::new(ParseTree::Num(1))
+        ));
+        let mut valid_parser = Parser::new();
+        valid_parser.parse(valid_tokens);
+        assert_eq!(valid_parser.tree.unwrap(), valid_tree);
+    }
+
+    #[test]
+    fn bitwise_precedence() {
+        let valid_tokens = vec![
+            Token::Ident(String::from("a")),
+            Token::Op(Operator::Or),
+            Token::Op(Operator::Not),
+            Token::Num(1),
+            Token::Op(Operator::ShiftLeft),
+            Token::Num(2),
+            Token::Op(Operator::Plus),
+            Token::Num(3),
+            Token::Op(Operator::And),
+            Token::Num(4),
+            Token::End
+        ];
+        let valid_tree = Box::new(ParseTree::Bit(
+            BitOp::Or,
+            Box::new(ParseTree::Var(String::from("a"))),
+            Box::new(ParseTree::Bit(
+                BitOp::And,
+                Box::new(ParseTree::Bit(
+                    BitOp::ShiftLeft,
+                    Box::new(ParseTree::Not(Box::new(ParseTree::Num(1)))),
+                    Box::new(ParseTree::Sum(
+                        SumOp::Plus,
+                        Box::new(ParseTree::Num(2)),
+                        Box::new(ParseTree::Num(3))
+                    ))
+                )),
+                Box::new(ParseTree::Num(4))
+            ))
+        ));
+        let mut valid_parser = Parser::new();
+        valid_parser.parse(valid_tokens);
+        assert_eq!(valid_parser.tree.unwrap(), valid_tree);
+    }
+
+    #[test]
+    fn power_grouping() {
+        let tokens = vec![
+            Token::Op(Operator::Minus),
+            Token::Num(2),
+            Token::Op(Operator::Power),
+            Token::Num(3),
+            Token::Op(Operator::Power),
+            Token::Num(2),
+            Token::End
+        ];
+        let tree = Box::new(ParseTree::Unary(
+            SumOp::Minus,
+            Box::new(ParseTree::Power(
+                Box::new(ParseTree::Num(2)),
+                Box::new(ParseTree::Power(
+                    Box::new(ParseTree::Num(3)),
+                    Box::new(ParseTree::Num(2))
+                ))
+            ))
+        ));
+        let mut parser = Parser::new();
+        parser.parse(tokens);
+        assert_eq!(parser.tree, Ok(tree));
+    }
+
+    #[test]
+    fn implicit_product() {
+        // 2 h / 30 min
+        let tokens = vec![
+            Token::Num(2),
+            Token::Ident(String::from("h")),
+            Token::Op(Operator::Divide),
+            Token::Num(30),
+            Token::Ident(String::from("min")),
+            Token::End
+        ];
+        let tree = Box::new(ParseTree::Prod(
+            ProdOp::Divide,
+            Box::new(ParseTree::Prod(
+                ProdOp::Times,
+                Box::new(ParseTree::Num(2)),
+                Box::new(ParseTree::Var(String::from("h")))
+            )),
+            Box::new(ParseTree::Prod(
+                ProdOp::Times,
+                Box::new(ParseTree::Num(30)),
+                Box::new(ParseTree::Var(String::from("min")))
+            ))
+        ));
+        let mut parser = Parser::new();
+        parser.parse(tokens);
+        assert_eq!(parser.tree, Ok(tree));
+        let mut parser = Parser::new();
+        parser.parse(vec![Token::Num(1), Token::Num(2), Token::End]);
+        assert_eq!(parser.tree, Err(String::from("Unexpected input")));
+    }
+
+    #[test]
+    fn function_calls() {
+        // 2 sqrt(x, 1) + f()
+        let tokens = vec![
+            Token::Num(2),
+            Token::Ident(String::from("sqrt")),
+            Token::LParen,
+            Token::Ident(String::from("x")),
+            Token::Comma,
+            Token::Num(1),
+            Token::RParen,
+            Token::Op(Operator::Plus),
+            Token::Ident(String::from("f")),
+            Token::LParen,
+            Token::RParen,
+            Token::End
+        ];
+        let tree = Box::new(ParseTree::Sum(
+            SumOp::Plus,
+            Box::new(ParseTree::Prod(
+                ProdOp::Times,
+                Box::new(ParseTree::Num(2)),
+                Box::new(ParseTree::Call(String::from("sqrt"), vec![ParseTree::Var(String::from("x")), ParseTree::Num(1)]))
+            )),
+            Box::new(ParseTree::Call(String::from("f"), Vec::new()))
+        ));
+        let mut parser = Parser::new();
+        parser.parse(tokens);
+        assert_eq!(parser.tree, Ok(tree));
+        let mut parser = Parser::new();
+        parser.parse(vec![Token::Ident(String::from("abs")), Token::LParen, Token::Num(1), Token::End]);
+        assert_eq!(parser.tree, Err(String::from("Missing right parenthesis")));
+        assert!(parser.incomplete);
+    }
+
+    #[test]
+    fn lists_and_indexing() {
+        // [[1], []][0][1] @ x
+        let tokens = vec![
+            Token::LBracket,
+            Token::LBracket,
+            Token::Num(1),
+            Token::RBracket,
+            Token::Comma,
+            Token::LBracket,
+            Token::RBracket,
+            Token::RBracket,
+            Token::LBracket,
+            Token::Num(0),
+            Token::RBracket,
+            Token::LBracket,
+            Token::Num(1),
+            Token::RBracket,
+            Token::Op(Operator::MatMul),
+            Token::Ident(String::from("x")),
+            Token::End
+        ];
+        let list = ParseTree::List(vec![ParseTree::List(vec![ParseTree::Num(1)]), ParseTree::List(Vec::new())]);
+        let tree = Box::new(ParseTree::Prod(
+            ProdOp::MatMul,
+            Box::new(ParseTree::Index(
+                Box::new(ParseTree::Index(Box::new(list), Box::new(ParseTree::Num(0)))),
+                Box::new(ParseTree::Num(1))
+            )),
+            Box::new(ParseTree::Var(String::from("x")))
+        ));
+        let mut parser = Parser::new();
+        parser.parse(tokens);
+        assert_eq!(parser.tree, Ok(tree));
+        let mut parser = Parser::new();
+        parser.parse(vec![Token::LBracket, Token::Num(1), Token::Comma, Token::End]);
+        assert!(parser.incomplete);
+        let mut parser = Parser::new();
+        parser.parse(vec![Token::Ident(String::from("x")), Token::LBracket, Token::Num(1), Token::RParen, Token::End]);
+        assert_eq!(parser.tree, Err(String::from("Missing right bracket")));
+    }
+
+    #[test]
+    fn ranges() {
+        // 1..n+1
+        let tokens = vec![
+            Token::Num(1),
+            Token::Op(Operator::Range),
+            Token::Ident(String::from("n")),
+            Token::Op(Operator::Plus),
+            Token::Num(1),
+            Token::End
+        ];
+        let tree = Box::new(ParseTree::Range(
+            Box::new(ParseTree::Num(1)),
+            Box::new(ParseTree::Sum(SumOp::Plus, Box::new(ParseTree::Var(String::from("n"))), Box::new(ParseTree::Num(1))))
+        ));
+        let mut parser = Parser::new();
+        parser.parse(tokens);
+        assert_eq!(parser.tree, Ok(tree));
+    }
+
+    #[test]
+    fn infix_text() {
+        let sum = |a, b| Box::new(ParseTree::Sum(SumOp::Minus, a, b));
+        let var = |name: &str| Box::new(ParseTree::Var(String::from(name)));
+        let tree = ParseTree::Prod(ProdOp::Divide, sum(var("a"), sum(var("b"), var("c"))), Box::new(ParseTree::Power(var("x"), Box::new(ParseTree::Num(2)))));
+        assert_eq!(tree.to_string(), "(a - (b - c))/x^2");
+        let negated = ParseTree::Power(Box::new(ParseTree::Unary(SumOp::Minus, var("x"))), Box::new(ParseTree::Real(0.5)));
+        assert_eq!(negated.to_string(), "(-x)^0.5");
+        let call = ParseTree::Index(Box::new(ParseTree::Call(String::from("f"), vec![*var("x"), ParseTree::Num(1)])), Box::new(ParseTree::Num(0)));
+        assert_eq!(call.to_string(), "f(x, 1)[0]");
+    }
+
+    #[test]
+    fn equations() {
+        // solve(x^2 = 2, x)
+        let tokens = vec![
+            Token::Ident(String::from("solve")),
+            Token::LParen,
+            Token::Ident(String::from("x")),
+            Token::Op(Operator::Power),
+            Token::Num(2),
+            Token::Assign,
+            Token::Num(2),
+            Token::Comma,
+            Token::Ident(String::from("x")),
+            Token::RParen,
+            Token::End
+        ];
+        let x = || ParseTree::Var(String::from("x"));
+        let equation = ParseTree::Equation(Box::new(ParseTree::Power(Box::new(x()), Box::new(ParseTree::Num(2)))), Box::new(ParseTree::Num(2)));
+        let mut parser = Parser::new();
+        parser.parse(tokens);
+        assert_eq!(parser.tree, Ok(Box::new(ParseTree::Call(String::from("solve"), vec![equation.clone(), x()]))));
+        assert_eq!(equation.to_string(), "x^2 = 2");
+    }
+
+    #[test]
+    fn factorials() {
+        // 2^n!^2
+        let tokens = vec![
+            Token::Num(2),
+            Token::Op(Operator::Power),
+            Token::Ident(String::from("n")),
+            Token::Op(Operator::Factorial),
+            Token::Op(Operator::Power),
+            Token::Num(2),
+            Token::End
+        ];
+        let factorial = ParseTree::Factorial(Box::new(ParseTree::Var(String::from("n"))));
+        let tree = ParseTree::Power(Box::new(ParseTree::Num(2)), Box::new(ParseTree::Power(Box::new(factorial), Box::new(ParseTree::Num(2)))));
+        let mut parser = Parser::new();
+        parser.parse(tokens);
+        assert_eq!(tree.to_string(), "2^n!^2");
+        assert_eq!(parser.tree, Ok(Box::new(tree)));
+        let negative = ParseTree::Factorial(Box::new(ParseTree::Unary(SumOp::Minus, Box::new(ParseTree::Num(3)))));
+        assert_eq!(negative.to_string(), "(-3)!");
+    }
+
+    #[test]
+    fn percentages() {
+        let parse = |tokens: Vec<Token>| {
+            let mut parser = Parser::new();
+            parser.parse(tokens);
+            parser.tree.unwrap()
+        };
+        let num = |x| Box::new(ParseTree::Num(x));
+        let percent = |x| Box::new(ParseTree::Percent(num(x)));
+        // 200 + 15% * 2
+        let tree = parse(vec![
+            Token::Num(200),
+            Token::Op(Operator::Plus),
+            Token::Num(15),
+            Token::Op(Operator::Percent),
+            Token::Op(Operator::Times),
+            Token::Num(2),
+            Token::End
+        ]);
+        assert_eq!(*tree, ParseTree::Sum(SumOp::Plus, num(200), Box::new(ParseTree::Prod(ProdOp::Times, percent(15), num(2)))));
+        assert_eq!(tree.to_string(), "200 + 15%*2");
+        // 15% of 200 * 2
+        let tree = parse(vec![
+            Token::Num(15),
+            Token::Op(Operator::Percent),
+            Token::Ident(String::from("of")),
+            Token::Num(200),
+            Token::Op(Operator::Times),
+            Token::Num(2),
+            Token::End
+        ]);
+        assert_eq!(*tree, ParseTree::Prod(ProdOp::Times, Box::new(ParseTree::Prod(ProdOp::Times, percent(15), num(200))), num(2)));
+        // 30 as % of 2^3
+        let tree = parse(vec![
+            Token::Num(30),
+            Token::Ident(String::from("as")),
+            Token::Op(Operator::Percent),
+            Token::Ident(String::from("of")),
+            Token::Num(2),
+            Token::Op(Operator::Power),
+            Token::Num(3),
+            Token::End
+        ]);
+        let power = Box::new(ParseTree::Power(num(2), num(3)));
+        assert_eq!(*tree, ParseTree::Prod(ProdOp::Divide, num(30), Box::new(ParseTree::Percent(power))));
+        assert_eq!(tree.to_string(), "30/(2^3)%");
+    }
+
+    #[test]
+    fn constants() {
+        // const g = 9.8
+        let tokens = vec![
+            Token::Ident(String::from("const")),
+            Token::Ident(String::from("g")),
+            Token::Assign,
+            Token::Decimal(String::from("9.8")),
+            Token::End
+        ];
+        let mut parser = Parser::new();
+        parser.parse(tokens);
+        let tree = ParseTree::Const(String::from("g"), Box::new(ParseTree::Decimal(String::from("9.8"))));
+        assert_eq!(tree.to_string(), "const g = 9.8");
+        assert_eq!(parser.tree, Ok(Box::new(tree)));
+        // const = 1
+        let mut parser = Parser::new();
+        parser.parse(vec![Token::Ident(String::from("const")), Token::Assign, Token::Num(1), Token::End]);
+        assert_eq!(parser.tree, Ok(Box::new(ParseTree::Assign(String::from("const"), Box::new(ParseTree::Num(1))))));
+    }
+
+    #[test]
+    fn definitions() {
+        // y := 2*x
+        let tokens = vec![
+            Token::Ident(String::from("y")),
+            Token::Define,
+            Token::Num(2),
+            Token::Op(Operator::Times),
+            Token::Ident(String::from("x")),
+            Token::End
+        ];
+        let mut parser = Parser::new();
+        parser.parse(tokens);
+        let product = ParseTree::Prod(ProdOp::Times, Box::new(ParseTree::Num(2)), Box::new(ParseTree::Var(String::from("x"))));
+        let tree = ParseTree::Define(String::from("y"), Box::new(product));
+        assert_eq!(tree.to_string(), "y := 2*x");
+        assert_eq!(parser.tree, Ok(Box::new(tree)));
+        // y := x = 1
+        let tokens = vec![
+            Token::Ident(String::from("y")),
+            Token::Define,
+            Token::Ident(String::from("x")),
+            Token::Assign,
+            Token::Num(1),
+            Token::End
+        ];
+        let mut parser = Parser::new();
+        parser.parse(tokens);
+        assert!(parser.tree.is_err());
+        let mut parser = Parser::new();
+        parser.parse(vec![Token::Num(1), Token::Define, Token::Num(2), Token::End]);
+        assert_eq!(parser.tree, Err(String::from("Only variables can be defined")));
+    }
+}
@@ -0,0 +1,1013 @@
+use super::lexer;
+use super::parser;
+use super::evaluator;
+use super::diagnostic::Diagnostic;
+use super::lexer::{Span, Token};
+use super::parser::ParseTree;
+use super::value::{Value, Width};
+use super::constants;
+use super::symbolic;
+use super::decimal::{self, Rounding};
+use super::format::{Format, Notation};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const HEADER: &str = "# rustkalkulator session";
+
+pub const COMMANDS: &[&str] = &["save", "load", "autosave", "reset", "format", "group", "prog", "symbolic", "deps", "precision", "rounding"];
+
+// The commands a saved session may run while it is read back.
+const SAVED_SETTINGS: &[&str] = &["symbolic", "precision", "rounding"];
+
+// How an answer is shown: in some notation, or counted in the unit it was
+// converted to.
+enum Shown {
+    Notation(Option<Notation>),
+    Unit(f64, String),
+}
+
+#[derive(Debug)]
+pub struct Session {
+    pub symbols: HashMap<String, Value>,
+    // Variables defined with `:=`, which are worked out again whenever
+    // one of their inputs changes.
+    pub definitions: HashMap<String, ParseTree>,
+    // Variables declared with `const`, which nothing may change.
+    pub constants: HashSet<String>,
+    pub autosave: Option<PathBuf>,
+    pub outputs: usize,
+    pub format: Format,
+    pub settings: evaluator::Settings,
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Session {
+            symbols: HashMap::new(),
+            definitions: HashMap::new(),
+            constants: HashSet::new(),
+            autosave: None,
+            outputs: 0,
+            format: Format::new(),
+            settings: evaluator::Settings::default(),
+        }
+    }
+
+    pub fn eval_line(&mut self, line: &str) -> Result<Value, String> {
+        self.evaluate(line).map_err(|e| e.message)
+    }
+
+    pub fn evaluate(&mut self, line: &str) -> Result<Value, Diagnostic> {
+        self.evaluate_formatted(line).map(|(answer, _)| answer)
+    }
+
+    fn evaluate_formatted(&mut self, line: &str) -> Result<(Value, Shown), Diagnostic> {
+        let mut tokenizer = lexer::Tokens::new(line.chars());
+        tokenizer.tokenize();
+        let mut parser = parser::Parser::new();
+        parser.parse(tokenizer.tokens.clone());
+        if let Err(e) = parser.tree {
+            let span = parser.error_at.and_then(|i| tokenizer.spans.get(i).copied());
+            return Err(Diagnostic::new(e, span));
+        }
+        let (tree, notation) = split_notation(parser.tree);
+        let target = match tree.as_deref() {
+            Ok(ParseTree::Convert(_, target)) => Some((**target).clone()),
+            _ => None
+        };
+        let whole = Span { start: 0, end: line.chars().count() };
+        let definition = match tree.as_deref() {
+            Ok(ParseTree::Define(name, formula)) => Some((name.clone(), (**formula).clone())),
+            _ => None
+        };
+        if let Some(cycle) = definition.as_ref().and_then(|(name, formula)| self.cycle(name, formula)) {
+            return Err(Diagnostic::new(format!("Circular definition: {}", cycle.join(" -> ")), Some(whole)));
+        }
+        let assigned = tree.as_deref().map(assigned).unwrap_or_default();
+        let names = assigned.iter().chain(definition.as_ref().map(|(name, _)| name));
+        if let Some(name) = names.clone().find(|name| self.constants.contains(*name)) {
+            return Err(Diagnostic::new(constants::read_only(name), Some(whole)));
+        }
+        let declared = match tree.as_deref() {
+            Ok(ParseTree::Const(name, _)) => Some(name.clone()),
+            _ => None
+        };
+        let before = self.symbols.clone();
+        let (definitions, constants) = (self.definitions.clone(), self.constants.clone());
+        let symbols = std::mem::take(&mut self.symbols);
+        let (answer, symbols) = evaluator::evaluate(tree, symbols, &self.settings);
+        self.symbols = symbols;
+        let answer = answer.map_err(|e| {
+            let span = self.locate_undefined(&e, &tokenizer.tokens, &tokenizer.spans).or(Some(whole));
+            Diagnostic::new(e, span)
+        })?;
+        // Functions like `solve(..., bind)` assign too, so make sure no
+        // constant changed on the way.
+        if let Some(name) = self.constants.iter().find(|name| self.symbols.get(*name) != before.get(*name)) {
+            let error = constants::read_only(name);
+            self.symbols = before;
+            return Err(Diagnostic::new(error, Some(whole)));
+        }
+        if let Some(notation) = notation.filter(|notation| !notation.shows(&answer)) {
+            self.symbols = before;
+            return Err(Diagnostic::new(format!("Only integers can be shown in {}", notation.name()), Some(whole)));
+        }
+        self.constants.extend(declared);
+        // A plain assignment replaces a definition with a fixed value.
+        for name in assigned {
+            self.definitions.remove(&name);
+        }
+        if let Some((name, formula)) = definition {
+            self.definitions.insert(name, formula);
+        }
+        // A definition that cannot take the new values undoes the whole line.
+        if let Err(e) = self.update(&before) {
+            self.symbols = before;
+            self.definitions = definitions;
+            self.constants = constants;
+            return Err(Diagnostic::new(e, Some(whole)));
+        }
+        match target {
+            None => Ok((answer, Shown::Notation(notation))),
+            // The whole line already checked out, so the target on its own
+            // evaluates and matches the answer's units.
+            Some(target) => {
+                let (size, _) = evaluator::evaluate(Ok(Box::new(target)), self.symbols.clone(), &self.settings);
+                let amount = size.and_then(|size| answer.convert(&size)).map_err(|e| Diagnostic::new(e, Some(whole)))?;
+                let at = tokenizer.tokens.iter().position(|token| *token == Token::Ident(String::from("to"))).unwrap_or(0);
+                let start = tokenizer.spans.get(at + 1).map_or(whole.end, |span| span.start);
+                let unit: String = line.chars().skip(start).collect();
+                Ok((answer, Shown::Unit(amount, unit.trim().to_string())))
+            }
+        }
+    }
+
+    pub fn is_incomplete(&self, line: &str) -> bool {
+        if line.trim_start().starts_with(':') {
+            return false;
+        }
+        let mut tokenizer = lexer::Tokens::new(line.chars());
+        tokenizer.tokenize();
+        let mut parser = parser::Parser::new();
+        parser.parse(tokenizer.tokens);
+        parser.incomplete
+    }
+
+    // Evaluation errors carry no position, so an undefined variable points
+    // at the first place the line reads it, and anything else at the whole line.
+    fn locate_undefined(&self, error: &str, tokens: &[Token], spans: &[Span]) -> Option<Span> {
+        let missing = error.strip_prefix("Undefined variable: ")?.split(' ').next()?;
+        tokens.iter().enumerate().find_map(|(i, token)| match token {
+            Token::Ident(name) if name == missing && !matches!(tokens.get(i + 1), Some(Token::Assign) | Some(Token::Define) | Some(Token::LParen)) => {
+                spans.get(i).copied()
+            },
+            _ => None
+        })
+    }
+
+    pub fn enter(&mut self, line: &str) -> Result<String, Diagnostic> {
+        let (answer, shown) = self.evaluate_formatted(line)?;
+        self.outputs += 1;
+        self.symbols.insert(format!("${}", self.outputs), answer.clone());
+        self.symbols.insert(String::from("ans"), answer.clone());
+        self.symbols.insert(String::from("_"), answer.clone());
+        let shown = match (shown, self.settings.width) {
+            (Shown::Unit(amount, unit), _) => format!("{} {}", self.format.show(Value::Real(amount)), unit),
+            (Shown::Notation(notation), Some(width)) => self.format.with(notation).word(answer, width),
+            (Shown::Notation(notation), None) => self.format.with(notation).show(answer)
+        };
+        Ok(format!("[{}] {}", self.outputs, shown))
+    }
+
+    // Works out again every definition that depends, directly or through
+    // other definitions, on a variable that changed since `before`.
+    fn update(&mut self, before: &HashMap<String, Value>) -> Result<(), String> {
+        let mut changed: HashSet<String> = self.symbols.iter()
+            .filter(|(name, value)| before.get(*name) != Some(value))
+            .map(|(name, _)| name.clone())
+            .collect();
+        changed.extend(before.keys().filter(|name| !self.symbols.contains_key(*name)).cloned());
+        for name in self.order() {
+            let formula = &self.definitions[&name];
+            if !inputs(formula).iter().any(|input| changed.contains(input)) {
+                continue;
+            }
+            let symbols = std::mem::take(&mut self.symbols);
+            let (value, symbols) = evaluator::evaluate(Ok(Box::new(formula.clone())), symbols, &self.settings);
+            self.symbols = symbols;
+            let value = value.map_err(|e| format!("Could not update {}: {}", name, e))?;
+            self.symbols.insert(name.clone(), value);
+            changed.insert(name);
+        }
+        Ok(())
+    }
+
+    // The definitions in an order where every one comes after those it uses.
+    fn order(&self) -> Vec<String> {
+        fn visit(name: &str, definitions: &HashMap<String, ParseTree>, seen: &mut HashSet<String>, order: &mut Vec<String>) {
+            if !seen.insert(name.to_string()) {
+                return;
+            }
+            if let Some(formula) = definitions.get(name) {
+                for input in inputs(formula) {
+                    visit(&input, definitions, seen, order);
+                }
+                order.push(name.to_string());
+            }
+        }
+        let mut names: Vec<&String> = self.definitions.keys().collect();
+        names.sort();
+        let (mut seen, mut order) = (HashSet::new(), Vec::new());
+        for name in names {
+            visit(name, &self.definitions, &mut seen, &mut order);
+        }
+        order
+    }
+
+    // The chain of definitions that would lead from `name` back to itself,
+    // if it were defined as `formula`.
+    fn cycle(&self, name: &str, formula: &ParseTree) -> Option<Vec<String>> {
+        fn path(from: &str, target: &str, definitions: &HashMap<String, ParseTree>, seen: &mut HashSet<String>) -> Option<Vec<String>> {
+            if from == target {
+                return Some(vec![target.to_string()]);
+            }
+            let formula = definitions.get(from).filter(|_| seen.insert(from.to_string()))?;
+            inputs(formula).iter().find_map(|input| path(input, target, definitions, seen)).map(|mut rest| {
+                rest.insert(0, from.to_string());
+                rest
+            })
+        }
+        let mut seen = HashSet::new();
+        inputs(formula).iter().find_map(|input| path(input, name, &self.definitions, &mut seen)).map(|mut rest| {
+            rest.insert(0, name.to_string());
+            rest
+        })
+    }
+
+    fn dependencies(&self, name: &str) -> String {
+        let list = |names: Vec<String>| if names.is_empty() { String::from("nothing") } else { names.join(", ") };
+        let mut users: Vec<String> = self.definitions.iter()
+            .filter(|(_, formula)| inputs(formula).iter().any(|input| input == name))
+            .map(|(user, _)| user.clone())
+            .collect();
+        users.sort();
+        let definition = match self.definitions.get(name) {
+            Some(formula) => format!("{} := {}\nDepends on: {}", name, formula, list(inputs(formula))),
+            None => format!("{} is not defined with :=", name)
+        };
+        format!("{}\nUsed by: {}", definition, list(users))
+    }
+
+    pub fn reset(&mut self) {
+        self.symbols.clear();
+        self.definitions.clear();
+        self.constants.clear();
+        self.outputs = 0;
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.symbols.keys().filter(|name| !name.starts_with('$')).cloned().collect()
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut names: Vec<&String> = self.symbols.keys()
+            .filter(|name| !is_output(name) && !self.definitions.contains_key(*name))
+            .collect();
+        names.sort();
+        let mut text = format!("{}\n", HEADER);
+        // Settings come first, since they decide how the values read back.
+        if self.settings.symbolic {
+            text.push_str(":symbolic on\n");
+        }
+        if let Some(precision) = self.settings.precision {
+            text.push_str(&format!(":precision {}\n", precision));
+        }
+        if self.settings.rounding != Rounding::default() {
+            text.push_str(&format!(":rounding {}\n", self.settings.rounding.name()));
+        }
+        for name in names {
+            let keyword = if self.constants.contains(name) { "const " } else { "" };
+            text.push_str(&format!("{}{} = {}\n", keyword, name, self.symbols[name]));
+        }
+        // Definitions go last, so that their inputs already have values.
+        for name in self.order() {
+            text.push_str(&format!("{} := {}\n", name, self.definitions[&name]));
+        }
+        text
+    }
+
+    pub fn run_script(&mut self, script: &str) -> Result<usize, String> {
+        let mut count = 0;
+        let mut pending = String::new();
+        let mut first = 0;
+        for (number, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if pending.is_empty() {
+                first = number;
+            } else {
+                pending.push(' ');
+            }
+            pending.push_str(line);
+            if self.is_incomplete(&pending) {
+                continue;
+            }
+            if let Err(e) = self.eval_line(&pending) {
+                return Err(format!("Line {}: {}", first + 1, e));
+            }
+            pending.clear();
+            count += 1;
+        }
+        if !pending.is_empty() {
+            if let Err(e) = self.eval_line(&pending) {
+                return Err(format!("Line {}: {}", first + 1, e));
+            }
+        }
+        Ok(count)
+    }
+
+    // Reads back what `serialize` wrote. Saved values are plain numbers and
+    // quantities, so they are worked out without the user's variables: a
+    // variable named `s` must not change what `3 m/s` means on a later line.
+    fn restore(&mut self, script: &str) -> Result<usize, String> {
+        let mut count = 0;
+        for (number, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.restore_line(line).map_err(|e| format!("Line {}: {}", number + 1, e))?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn restore_line(&mut self, line: &str) -> Result<(), String> {
+        if let Some(command) = line.strip_prefix(':') {
+            return match command.split_whitespace().next() {
+                Some(name) if SAVED_SETTINGS.contains(&name) => self.command(line).map(|_| ()),
+                _ => Err(format!("{} is not a setting", line))
+            };
+        }
+        let mut tokenizer = lexer::Tokens::new(line.chars());
+        tokenizer.tokenize();
+        let mut parser = parser::Parser::new();
+        parser.parse(tokenizer.tokens);
+        let (name, tree, constant) = match *parser.tree? {
+            ParseTree::Assign(name, tree) => (name, tree, false),
+            ParseTree::Const(name, tree) => (name, tree, true),
+            // Definitions are written last and read their inputs as usual.
+            _ => return self.eval_line(line).map(|_| ())
+        };
+        if self.constants.contains(&name) || constants::lookup(&name).is_some() {
+            return Err(constants::read_only(&name));
+        }
+        // Formulas were saved for their variables that had no value, and
+        // read back as formulas whatever the mode.
+        let settings = evaluator::Settings { symbolic: true, ..self.settings };
+        let (value, _) = evaluator::evaluate(Ok(tree), HashMap::new(), &settings);
+        let before = self.symbols.clone();
+        self.symbols.insert(name.clone(), value?);
+        self.definitions.remove(&name);
+        if constant {
+            self.constants.insert(name);
+        }
+        self.update(&before)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<usize, String> {
+        match fs::write(path, self.serialize()) {
+            Ok(_) => Ok(self.symbols.keys().filter(|name| !is_output(name)).count()),
+            Err(e) => Err(format!("Could not save to {}: {}", path.display(), e))
+        }
+    }
+
+    pub fn load(&mut self, path: &Path) -> Result<usize, String> {
+        match fs::read_to_string(path) {
+            Ok(script) if script.starts_with(HEADER) => self.restore(&script),
+            Ok(script) => self.run_script(&script),
+            Err(e) => Err(format!("Could not load {}: {}", path.display(), e))
+        }
+    }
+
+    pub fn command(&mut self, line: &str) -> Result<String, String> {
+        let mut words = line[1..].split_whitespace();
+        let name = words.next().unwrap_or("");
+        let argument: Vec<&str> = words.collect();
+        let argument = argument.join(" ");
+        match (name, argument.as_str()) {
+            ("save", "") | ("load", "") => Err(format!("Usage: :{} <file>", name)),
+            ("save", file) => {
+                self.save(Path::new(file)).map(|n| format!("Saved {} variables to {}", n, file))
+            },
+            ("load", file) => {
+                self.load(Path::new(file)).map(|n| format!("Loaded {} lines from {}", n, file))
+            },
+            ("autosave", "") => match &self.autosave {
+                Some(path) => Ok(format!("Autosave to {}", path.display())),
+                None => Ok(String::from("Autosave is off"))
+            },
+            ("autosave", "off") => {
+                self.autosave = None;
+                Ok(String::from("Autosave is off"))
+            },
+            ("autosave", file) => {
+                self.autosave = Some(PathBuf::from(file));
+                Ok(format!("Autosave to {}", file))
+            },
+            ("reset", "") => {
+                self.reset();
+                Ok(String::from("Cleared all variables and outputs"))
+            },
+            ("format", "") => Ok(self.format.describe()),
+            ("format", setting) => {
+                let mut words = setting.split_whitespace();
+                let notation = words.next().and_then(Format::notation);
+                let precision = words.next().map(|p| p.parse::<usize>());
+                match (notation, precision, words.next()) {
+                    (Some(notation), None, None) => self.format.notation = notation,
+                    (Some(notation), Some(Ok(precision)), None) => {
+                        self.format.notation = notation;
+                        self.format.precision = Some(precision);
+                    },
+                    _ => return Err(format!("Usage: :format <{}> [precision]", Format::names().join("|")))
+                };
+                Ok(self.format.describe())
+            },
+            ("group", "off") => {
+                self.format.grouping = None;
+                Ok(self.format.describe())
+            },
+            ("group", "_") | ("group", ",") => {
+                self.format.grouping = argument.chars().next();
+                Ok(self.format.describe())
+            },
+            ("group", _) => Err(String::from("Usage: :group <_|,|off>")),
+            ("prog", "") => match self.settings.width {
+                Some(width) => Ok(format!("Programmer mode: {}", width.name())),
+                None => Ok(String::from("Programmer mode is off"))
+            },
+            ("prog", "off") => {
+                self.settings.width = None;
+                Ok(String::from("Programmer mode is off"))
+            },
+            ("prog", name) => match Width::parse(name) {
+                Some(width) => {
+                    self.settings.width = Some(width);
+                    if self.format.notation == Notation::Decimal {
+                        self.format.notation = Notation::Hex;
+                    }
+                    Ok(format!("Programmer mode: {}", width.name()))
+                },
+                None => Err(String::from("Usage: :prog <u8|u16|u32|u64|i8|i16|i32|i64|off>"))
+            },
+            ("symbolic", "") => Ok(self.describe_symbolic()),
+            ("symbolic", "on") | ("symbolic", "off") => {
+                self.settings.symbolic = argument == "on";
+                Ok(self.describe_symbolic())
+            },
+            ("symbolic", _) => Err(String::from("Usage: :symbolic <on|off>")),
+            ("deps", "") => Err(String::from("Usage: :deps <variable>")),
+            ("deps", name) => Ok(self.dependencies(name)),
+            ("precision", "") => Ok(self.describe_precision()),
+            ("precision", "off") => {
+                self.settings.precision = None;
+                Ok(self.describe_precision())
+            },
+            ("precision", digits) => match digits.parse::<u32>() {
+                Ok(precision) if (1..=decimal::MAX_PRECISION).contains(&precision) => {
+                    self.settings.precision = Some(precision);
+                    Ok(self.describe_precision())
+                },
+                _ => Err(format!("Usage: :precision <1-{}|off>", decimal::MAX_PRECISION))
+            },
+            ("rounding", "") => Ok(format!("Rounding: {}", self.settings.rounding.name())),
+            ("rounding", mode) => match Rounding::parse(mode) {
+                Some(rounding) => {
+                    self.settings.rounding = rounding;
+                    Ok(format!("Rounding: {}", rounding.name()))
+                },
+                None => Err(format!("Usage: :rounding <{}>", Rounding::names().join("|")))
+            },
+            _ => Err(format!("Unknown command: :{}", name))
+        }
+    }
+
+    fn describe_symbolic(&self) -> String {
+        match self.settings.symbolic {
+            true => String::from("Symbolic mode is on: undefined variables make formulas"),
+            false => String::from("Symbolic mode is off")
+        }
+    }
+
+    fn describe_precision(&self) -> String {
+        match self.settings.precision {
+            Some(precision) => format!("Decimal mode: {} digits, rounding {}", precision, self.settings.rounding.name()),
+            None => String::from("Decimal mode is off")
+        }
+    }
+
+    pub fn finish(&self) -> Option<Result<usize, String>> {
+        self.autosave.as_ref().map(|path| self.save(path))
+    }
+}
+
+// Outputs belong to the session they were worked out in, so `ans`, `_` and
+// `$n` are neither saved nor counted as variables.
+fn is_output(name: &str) -> bool {
+    name.starts_with('$') || name == "ans" || name == "_"
+}
+
+// The distinct variables a formula reads.
+fn inputs(formula: &ParseTree) -> Vec<String> {
+    let mut names = symbolic::variables(formula);
+    let mut seen = HashSet::new();
+    names.retain(|name| seen.insert(name.clone()));
+    names
+}
+
+// The names a line assigns to, as in `a = b = 1`.
+fn assigned(tree: &ParseTree) -> Vec<String> {
+    match tree {
+        ParseTree::Assign(name, tree) | ParseTree::Const(name, tree) => [vec![name.clone()], assigned(tree)].concat(),
+        _ => Vec::new()
+    }
+}
+
+// A trailing `to hex`, `to sci` and so on only picks how the answer is shown.
+fn split_notation(tree: Result<Box<ParseTree>, String>) -> (Result<Box<ParseTree>, String>, Option<Notation>) {
+    match tree.map(|tree| *tree) {
+        Ok(ParseTree::Convert(inner, target)) => match *target {
+            ParseTree::Var(ref name) if Format::notation(name).is_some() => (Ok(inner), Format::notation(name)),
+            target => (Ok(Box::new(ParseTree::Convert(inner, Box::new(target)))), None)
+        },
+        Ok(tree) => (Ok(Box::new(tree)), None),
+        Err(e) => (Err(e), None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn serialize_sorted() {
+        let mut session = Session::new();
+        session.eval_line("y = -4").unwrap();
+        session.eval_line("x = 3").unwrap();
+        assert_eq!(session.serialize(), format!("{}\nx = 3\ny = -4\n", HEADER));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut session = Session::new();
+        session.eval_line("rate = 7").unwrap();
+        session.eval_line("total = rate * (0 - 12)").unwrap();
+        let mut restored = Session::new();
+        restored.run_script(&session.serialize()).unwrap();
+        assert_eq!(restored.symbols, session.symbols);
+        assert_eq!(restored.serialize(), session.serialize());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir().join(format!("rustkalkulator-{}.calc", std::process::id()));
+        let mut session = Session::new();
+        session.eval_line("x = 42").unwrap();
+        session.eval_line("k = 40!").unwrap();
+        assert_eq!(session.command(&format!(":save {}", path.display())), Ok(format!("Saved 2 variables to {}", path.display())));
+        let mut restored = Session::new();
+        assert!(restored.command(&format!(":load {}", path.display())).is_ok());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(restored.eval_line("x"), Ok(Value::Int(42)));
+        assert_eq!(restored.eval_line("k"), session.eval_line("k"));
+    }
+
+    #[test]
+    fn script_error_line() {
+        let mut session = Session::new();
+        let result = session.run_script("# comment\nx = 1\n\ny = z");
+        assert_eq!(result, Err(String::from("Line 4: Undefined variable: z")));
+    }
+
+    #[test]
+    fn unknown_command() {
+        let mut session = Session::new();
+        assert!(session.command(":frobnicate").is_err());
+        assert!(session.command(":save").is_err());
+    }
+
+    #[test]
+    fn numbered_outputs() {
+        let mut session = Session::new();
+        assert_eq!(session.enter("1 + 2"), Ok(String::from("[1] 3")));
+        assert_eq!(session.enter("ans * 2"), Ok(String::from("[2] 6")));
+        assert!(session.enter("y").is_err());
+        assert_eq!(session.enter("$1 + $2 + _"), Ok(String::from("[3] 15")));
+        assert_eq!(session.serialize(), format!("{}\n", HEADER));
+    }
+
+    #[test]
+    fn reset_outputs() {
+        let mut session = Session::new();
+        session.enter("x = 4").unwrap();
+        assert!(session.command(":reset").is_ok());
+        assert_eq!(session.eval_line("$1"), Err(String::from("No output $1")));
+        assert!(session.eval_line("x").is_err());
+        assert_eq!(session.enter("5"), Ok(String::from("[1] 5")));
+    }
+
+    #[test]
+    fn error_spans() {
+        let mut session = Session::new();
+        session.eval_line("rate = 3").unwrap();
+        let parse_error = session.evaluate("1 + * 2").unwrap_err();
+        assert_eq!(parse_error.span, Some(Span { start: 4, end: 5 }));
+        let lexer_error = session.evaluate("2 + 3#").unwrap_err();
+        assert_eq!(lexer_error.span, Some(Span { start: 4, end: 6 }));
+        let undefined = session.evaluate("total = rate * count").unwrap_err();
+        assert_eq!(undefined.span, Some(Span { start: 15, end: 20 }));
+        let division = session.evaluate("rate / 0").unwrap_err();
+        assert_eq!(division.span, Some(Span { start: 0, end: 8 }));
+        let division = session.evaluate("1/0 + y").unwrap_err();
+        assert_eq!(division.span, Some(Span { start: 0, end: 7 }));
+        let shapes = session.evaluate("[1,2]+[1]+zz").unwrap_err();
+        assert_eq!(shapes.span, Some(Span { start: 0, end: 12 }));
+        let bound = session.evaluate("integrate(1/x, x, 0, 1) + sum(1/k, k, 0, 2)").unwrap_err();
+        assert_eq!(bound.span, Some(Span { start: 0, end: 43 }));
+        let inner = session.evaluate("integrate(x * w, x, 0, 1)").unwrap_err();
+        assert_eq!(inner.span, Some(Span { start: 14, end: 15 }));
+    }
+
+    #[test]
+    fn incomplete_lines() {
+        let session = Session::new();
+        assert!(session.is_incomplete("(1 +"));
+        assert!(session.is_incomplete("2 *"));
+        assert!(session.is_incomplete("x ="));
+        assert!(!session.is_incomplete("(1 + 2)"));
+        assert!(!session.is_incomplete("1 + )"));
+        assert!(!session.is_incomplete("1 + 2#"));
+        assert!(!session.is_incomplete(":save"));
+    }
+
+    #[test]
+    fn script_continuation() {
+        let mut session = Session::new();
+        assert_eq!(session.run_script("x = (1 +\n  2) *\n3\ny = x"), Ok(2));
+        assert_eq!(session.eval_line("y"), Ok(Value::Int(9)));
+        assert_eq!(session.run_script("z = 1\nw = (2 +"), Err(String::from("Line 2: Unexpected end of input")));
+    }
+
+    #[test]
+    fn real_round_trip() {
+        let mut session = Session::new();
+        session.eval_line("small = 1.0 / 3").unwrap();
+        session.eval_line("large = -2.5e300").unwrap();
+        session.eval_line("whole = 4.0").unwrap();
+        let mut restored = Session::new();
+        restored.run_script(&session.serialize()).unwrap();
+        assert_eq!(restored.symbols, session.symbols);
+    }
+
+    #[test]
+    fn output_format() {
+        let mut session = Session::new();
+        assert_eq!(session.enter("255 to hex"), Ok(String::from("[1] 0xff")));
+        assert_eq!(session.eval_line("x = 255.5 to hex"), Err(String::from("Only integers can be shown in hex")));
+        assert!(session.eval_line("x").is_err());
+        assert_eq!(session.eval_line("$1"), Ok(Value::Int(255)));
+        assert!(session.command(":format bin").is_ok());
+        assert!(session.command(":group _").is_ok());
+        assert_eq!(session.enter("x = 10"), Ok(String::from("[2] 0b1010")));
+        assert_eq!(session.enter("1.5 * 1000 to fixed"), Ok(String::from("[3] 1_500.000000")));
+        assert_eq!(session.command(":format sci 2"), Ok(String::from("Format: sci, precision 2, grouping '_'")));
+        assert_eq!(session.enter("x"), Ok(String::from("[4] 1.00e1")));
+        assert!(session.command(":format roman").is_err());
+        assert!(session.enter("x to furlongs").is_err());
+    }
+
+    #[test]
+    fn programmer_mode() {
+        let mut session = Session::new();
+        assert_eq!(session.command(":prog u8"), Ok(String::from("Programmer mode: u8")));
+        assert_eq!(session.enter("0xf0 | 0x0f"), Ok(String::from("[1] 0xff")));
+        assert_eq!(session.enter("$1 + 1"), Ok(String::from("[2] 0x00")));
+        assert_eq!(session.enter("~0b1010 to bin"), Ok(String::from("[3] 0b11110101")));
+        assert!(session.enter("1 << 8").is_err());
+        assert!(session.command(":prog i8").is_ok());
+        assert_eq!(session.enter("127 + 1 to dec"), Ok(String::from("[4] -128")));
+        assert_eq!(session.enter("-1"), Ok(String::from("[5] 0xff")));
+        assert!(session.enter("1.5").is_err());
+        assert!(session.command(":prog u12").is_err());
+        assert!(session.command(":prog off").is_ok());
+        assert_eq!(session.enter("1 << 8 xor 6 & 3"), Ok(String::from("[6] 0x102")));
+        assert!(session.command(":prog u64").is_ok());
+        assert_eq!(session.enter("40!"), Ok(String::from("[7] 0xff05254000000000")));
+    }
+
+    #[test]
+    fn unit_arithmetic() {
+        let mut session = Session::new();
+        assert_eq!(session.enter("3 m + 20 cm"), Ok(String::from("[1] 3.2 m")));
+        assert_eq!(session.enter("60 km/h * 2 h to km"), Ok(String::from("[2] 120 km")));
+        assert_eq!(session.enter("5 kg * 9.81 m/s^2"), Ok(String::from("[3] 49.05 N")));
+        assert_eq!(session.enter("100 km/h to m/s"), Ok(String::from("[4] 27.7777777777778 m/s")));
+        assert_eq!(session.enter("1 mi to ft"), Ok(String::from("[5] 5280 ft")));
+        assert_eq!(session.enter("2 h / 30 min"), Ok(String::from("[6] 4")));
+        let error = session.evaluate("3 m + 2 s").unwrap_err();
+        assert_eq!(error.message, "Cannot add m and s");
+        assert_eq!(error.span, Some(Span { start: 0, end: 9 }));
+        assert_eq!(session.eval_line("3 m to kg"), Err(String::from("Cannot convert m to kg")));
+        assert_eq!(session.eval_line("3 m to 2"), Err(String::from("Cannot convert to an expression")));
+        assert_eq!(session.eval_line("3 m to 5 m"), Err(String::from("Convert to units alone, as in `to m` rather than `to 5 m`")));
+        assert_eq!(session.eval_line("3 m to 0.5 km"), Err(String::from("Convert to units alone, as in `to m` rather than `to 5 m`")));
+        assert_eq!(session.enter("2 / s to 1/min"), Ok(String::from("[7] 120 1/min")));
+        assert_eq!(session.enter("3 m^2 to cm^2"), Ok(String::from("[8] 30000 cm^2")));
+    }
+
+    #[test]
+    fn unit_round_trip() {
+        let mut session = Session::new();
+        session.eval_line("speed = 100 km/h").unwrap();
+        session.eval_line("rate = 3 / s").unwrap();
+        session.eval_line("m = 2").unwrap();
+        assert_eq!(session.eval_line("3 m"), Ok(Value::Int(6)));
+        let mut restored = Session::new();
+        restored.restore(&session.serialize()).unwrap();
+        assert_eq!(restored.symbols, session.symbols);
+    }
+
+    #[test]
+    fn complex_numbers() {
+        let mut session = Session::new();
+        assert_eq!(session.enter("z = 3+4i"), Ok(String::from("[1] 3 + 4i")));
+        assert_eq!(session.enter("abs(z) + re(z) * im(z)"), Ok(String::from("[2] 17")));
+        assert_eq!(session.enter("sqrt(-1)"), Ok(String::from("[3] i")));
+        assert_eq!(session.enter("conj(z) * i"), Ok(String::from("[4] 4 + 3i")));
+        assert_eq!(session.enter("i^2"), Ok(String::from("[5] -1")));
+        assert!(session.command(":format polar 2").is_ok());
+        assert_eq!(session.enter("2i"), Ok(String::from("[6] 2.00 ∠ 1.57")));
+        assert_eq!(session.eval_line("abs(1, 2)"), Err(String::from("abs takes 1 argument, got 2")));
+        assert_eq!(session.evaluate("sqrt(x)").unwrap_err().span, Some(Span { start: 5, end: 6 }));
+        let mut restored = Session::new();
+        restored.run_script(&session.serialize()).unwrap();
+        assert_eq!(restored.serialize(), session.serialize());
+    }
+
+    #[test]
+    fn matrices() {
+        let mut session = Session::new();
+        assert_eq!(session.enter("m = [[1, 2], [3, 4]]"), Ok(String::from("[1] [[1, 2], [3, 4]]")));
+        assert_eq!(session.enter("m[0][1] * 10"), Ok(String::from("[2] 20")));
+        assert_eq!(session.enter("m + 1"), Ok(String::from("[3] [[2, 3], [4, 5]]")));
+        assert_eq!(session.enter("m @ [1, -1]"), Ok(String::from("[4] [-1, -1]")));
+        assert_eq!(session.enter("det(m) * transpose(m)[0]"), Ok(String::from("[5] [-2, -6]")));
+        assert_eq!(session.enter("inverse([[2, 0], [0, 4]])"), Ok(String::from("[6] [[0.5, 0], [0, 0.25]]")));
+        assert_eq!(session.eval_line("[1, 2] + [1, 2, 3]"), Err(String::from("Lists have different lengths: 2 and 3")));
+        assert_eq!(session.eval_line("m @ [[1, 2, 3]]"), Err(String::from("Cannot multiply a 2x2 matrix by a 1x3 matrix")));
+        assert_eq!(session.eval_line("m[2]"), Err(String::from("Index 2 out of range for a list of length 2")));
+        assert!(session.is_incomplete("v = [1, 2,"));
+        let mut restored = Session::new();
+        restored.run_script(&session.serialize()).unwrap();
+        assert_eq!(restored.serialize(), session.serialize());
+    }
+
+    #[test]
+    fn statistics() {
+        let mut session = Session::new();
+        assert_eq!(session.enter("data = [2, 4, 4, 4, 5, 5, 7, 9]"), Ok(String::from("[1] [2, 4, 4, 4, 5, 5, 7, 9]")));
+        assert_eq!(session.enter("mean(data) + median(data)"), Ok(String::from("[2] 9.5")));
+        assert_eq!(session.enter("max(data) - min(3, 1, 2)"), Ok(String::from("[3] 8")));
+        assert_eq!(session.enter("sum(1..100)"), Ok(String::from("[4] 5050")));
+        assert!(session.eval_line("count(2..n+1)").is_err());
+        assert_eq!(session.enter("range(0, 1, 0.25)"), Ok(String::from("[5] [0, 0.25, 0.5, 0.75, 1]")));
+        assert_eq!(session.enter("percentile([1, 2, 3, 4], 50)"), Ok(String::from("[6] 2.5")));
+        assert_eq!(session.enter("mean([1 m, 2 m, 6 m])"), Ok(String::from("[7] 3 m")));
+        assert_eq!(session.enter("2 min to s"), Ok(String::from("[8] 120 s")));
+        assert_eq!(session.eval_line("median([])"), Err(String::from("median of an empty list")));
+        assert_eq!(session.eval_line("max([1 m, 2 s])"), Err(String::from("Cannot subtract s from m")));
+    }
+
+    #[test]
+    fn derivatives() {
+        let mut session = Session::new();
+        assert_eq!(session.enter("diff(x^3 - 2*x, x)"), Ok(String::from("[1] 3*x^2 - 2")));
+        assert_eq!(session.enter("diff(diff(x^3, x), x)"), Ok(String::from("[2] 6*x")));
+        session.eval_line("x = 2").unwrap();
+        assert_eq!(session.enter("diff(x^3 - 2*x, x)"), Ok(String::from("[3] 10")));
+        assert_eq!(session.enter("diff(ln(x), x)"), Ok(String::from("[4] 0.5")));
+        assert_eq!(session.enter("diff(a*x, x)"), Ok(String::from("[5] a")));
+        assert_eq!(session.enter("diff(sin(x), x) - cos(x)"), Ok(String::from("[6] 0")));
+        assert_eq!(session.eval_line("diff(x^2, 2)"), Err(String::from("diff takes an expression and a variable, like diff(x^2, x)")));
+        assert_eq!(session.eval_line("$5 + 1"), Err(String::from("Cannot calculate with a formula until its variables have values")));
+        assert_eq!(session.serialize(), format!("{}\nx = 2\n", HEADER));
+    }
+
+    #[test]
+    fn simplification() {
+        let mut session = Session::new();
+        assert_eq!(session.enter("simplify(2 x + 3 x - y + y)"), Ok(String::from("[1] 5*x")));
+        assert_eq!(session.enter("d = diff(x^2 + x, x)"), Ok(String::from("[2] 2*x + 1")));
+        assert_eq!(session.enter("simplify(d*(x - 1) - x)"), Ok(String::from("[3] 2*x^2 - 2*x - 1")));
+        session.eval_line("x = 3").unwrap();
+        assert_eq!(session.enter("simplify(x^2 + x*x)"), Ok(String::from("[4] 18")));
+        assert_eq!(session.eval_line("simplify(1, 2)"), Err(String::from("simplify takes 1 argument, got 2")));
+    }
+
+    #[test]
+    fn equations() {
+        let mut session = Session::new();
+        session.eval_line("x = 10").unwrap();
+        assert_eq!(session.enter("solve(x^3 = 27, x)"), Ok(String::from("[1] 3")));
+        assert_eq!(session.eval_line("x"), Ok(Value::Int(10)));
+        assert_eq!(session.eval_line("x = 1 = 2"), Err(String::from("Only variables can be assigned to")));
+        assert_eq!(session.eval_line("[x = 1]"), Err(String::from("Equations can only be solved, as in solve(x^2 = 2, x)")));
+    }
+
+    #[test]
+    fn linear_systems() {
+        let mut session = Session::new();
+        session.eval_line("k = 5").unwrap();
+        assert_eq!(session.enter("solve([2*x + y = k, x - y = 1], [x, y])"), Ok(String::from("[1] [2, 1]")));
+        assert!(session.eval_line("x").is_err());
+        assert_eq!(session.enter("solve([2*x + y = k, x - y = 1], [x, y], bind)"), Ok(String::from("[2] [2, 1]")));
+        assert_eq!(session.enter("x*10 + y"), Ok(String::from("[3] 21")));
+    }
+
+    #[test]
+    fn series_and_integrals() {
+        let mut session = Session::new();
+        session.eval_line("k = 7").unwrap();
+        assert_eq!(session.enter("sum(k^2, k, 1, 3)"), Ok(String::from("[1] 14")));
+        assert_eq!(session.enter("k"), Ok(String::from("[2] 7")));
+        assert_eq!(session.enter("integrate(2*x, x, 0, k)"), Ok(String::from("[3] 49")));
+        assert!(session.eval_line("x").is_err());
+    }
+
+    #[test]
+    fn symbolic_mode() {
+        let mut session = Session::new();
+        assert!(session.eval_line("y = 2*x + 1").is_err());
+        assert!(session.command(":symbolic on").is_ok());
+        assert_eq!(session.enter("y = 2*x + 1"), Ok(String::from("[1] 2*x + 1")));
+        assert_eq!(session.enter("z = y^2"), Ok(String::from("[2] y^2")));
+        assert_eq!(session.enter("z"), Ok(String::from("[3] (2*x + 1)^2")));
+        session.eval_line("x = 3").unwrap();
+        assert_eq!(session.enter("y"), Ok(String::from("[4] 7")));
+        assert_eq!(session.enter("z"), Ok(String::from("[5] 49")));
+        session.eval_line("x = 4").unwrap();
+        assert_eq!(session.enter("y + z"), Ok(String::from("[6] 90")));
+        assert_eq!(session.eval_line("w = w + 1"), Err(String::from("w cannot be defined in terms of itself")));
+        session.eval_line("a = b + 1").unwrap();
+        assert_eq!(session.eval_line("b = a"), Err(String::from("b cannot be defined in terms of itself")));
+        assert_eq!(session.enter("b = 2"), Ok(String::from("[7] 2")));
+        assert_eq!(session.enter("a"), Ok(String::from("[8] 3")));
+        assert_eq!(session.eval_line("1/0"), Err(String::from("Division by zero")));
+        assert!(session.command(":symbolic off").is_ok());
+        assert!(session.eval_line("2*v").is_err());
+    }
+
+    #[test]
+    fn definitions() {
+        let mut session = Session::new();
+        session.eval_line("a = 2").unwrap();
+        assert_eq!(session.enter("b := a + 1"), Ok(String::from("[1] 3")));
+        assert_eq!(session.enter("w := b*10"), Ok(String::from("[2] 30")));
+        session.eval_line("a = 5").unwrap();
+        assert_eq!(session.enter("w"), Ok(String::from("[3] 60")));
+        assert_eq!(session.eval_line("a := w - 1"), Err(String::from("Circular definition: a -> w -> b -> a")));
+        assert_eq!(session.eval_line("d := d + 1"), Err(String::from("Circular definition: d -> d")));
+        assert_eq!(session.eval_line("a"), Ok(Value::Int(5)));
+        assert_eq!(session.command(":deps b"), Ok(String::from("b := a + 1\nDepends on: a\nUsed by: w")));
+        assert_eq!(session.command(":deps a"), Ok(String::from("a is not defined with :=\nUsed by: b")));
+        assert!(session.serialize().ends_with("a = 5\nb := a + 1\nw := b*10\n"));
+        // Assigning a value ends the definition.
+        session.eval_line("b = 0").unwrap();
+        session.eval_line("a = 7").unwrap();
+        assert_eq!(session.eval_line("w"), Ok(Value::Int(0)));
+        assert_eq!(session.command(":deps b"), Ok(String::from("b is not defined with :=\nUsed by: w")));
+        session.eval_line("b = [1]").unwrap();
+        assert_eq!(session.eval_line("f := 1 - b[0]"), Ok(Value::Int(0)));
+        assert_eq!(session.eval_line("b = 1"), Err(String::from("Could not update f: Only lists can be indexed")));
+        assert_eq!(session.eval_line("b"), Ok(Value::List(vec![Value::Int(1)])));
+        assert_eq!(session.eval_line("f"), Ok(Value::Int(0)));
+        assert_eq!(session.eval_line("b := 2"), Err(String::from("Could not update f: Only lists can be indexed")));
+        assert_eq!(session.command(":deps b"), Ok(String::from("b is not defined with :=\nUsed by: f, w")));
+    }
+
+    #[test]
+    fn constants() {
+        let mut session = Session::new();
+        assert_eq!(session.enter("2*pi"), Ok(String::from("[1] 6.28318530717959")));
+        assert_eq!(session.enter("1 h to min"), Ok(String::from("[2] 60 min")));
+        assert_eq!(session.eval_line("pi = 3"), Err(String::from("pi is a constant and cannot be changed")));
+        assert_eq!(session.eval_line("x = e = 2"), Err(String::from("e is a constant and cannot be changed")));
+        assert_eq!(session.enter("const g = 9.81"), Ok(String::from("[3] 9.81")));
+        assert_eq!(session.eval_line("g = 10"), Err(String::from("g is a constant and cannot be changed")));
+        assert_eq!(session.eval_line("const g = 10"), Err(String::from("g is a constant and cannot be changed")));
+        assert_eq!(session.eval_line("g := 10"), Err(String::from("g is a constant and cannot be changed")));
+        assert_eq!(session.eval_line("solve([g + y = 1, y = 2], [g, y], bind)"), Err(String::from("g is a constant and cannot be changed")));
+        assert_eq!(session.eval_line("y"), Err(String::from("Undefined variable: y")));
+        assert_eq!(session.eval_line("g"), Ok(Value::Real(9.81)));
+        assert!(session.serialize().contains("const g = 9.81\n"));
+        session.reset();
+        assert!(session.eval_line("g = 10").is_ok());
+    }
+
+    #[test]
+    fn decimal_mode() {
+        let mut session = Session::new();
+        assert_eq!(session.command(":precision 50"), Ok(String::from("Decimal mode: 50 digits, rounding half-even")));
+        assert_eq!(session.enter("1/3"), Ok(format!("[1] 0.{}", "3".repeat(50))));
+        assert_eq!(session.command(":precision 3"), Ok(String::from("Decimal mode: 3 digits, rounding half-even")));
+        assert_eq!(session.enter("2.345 + 0"), Ok(String::from("[2] 2.34")));
+        assert_eq!(session.command(":rounding half-up"), Ok(String::from("Rounding: half-up")));
+        assert_eq!(session.enter("2.345 + 0"), Ok(String::from("[3] 2.35")));
+        assert!(session.command(":rounding up").is_err());
+        assert!(session.command(":precision 0").is_err());
+        assert_eq!(session.command(":precision off"), Ok(String::from("Decimal mode is off")));
+        assert_eq!(session.enter("1/3"), Ok(String::from("[4] 0")));
+    }
+
+    #[test]
+    fn save_skips_outputs() {
+        let path = std::env::temp_dir().join(format!("rustkalkulator-outputs-{}.calc", std::process::id()));
+        let mut session = Session::new();
+        session.enter("x = 42").unwrap();
+        session.enter("x + 1").unwrap();
+        assert_eq!(session.command(&format!(":save {}", path.display())), Ok(format!("Saved 1 variables to {}", path.display())));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(session.serialize(), format!("{}\nx = 42\n", HEADER));
+    }
+
+    #[test]
+    fn load_with_shadowed_units() {
+        let path = std::env::temp_dir().join(format!("rustkalkulator-units-{}.calc", std::process::id()));
+        let mut session = Session::new();
+        session.eval_line("s = 5 s").unwrap();
+        session.eval_line("v = 3 m/s").unwrap();
+        session.eval_line("m = 2 m").unwrap();
+        session.command(&format!(":save {}", path.display())).unwrap();
+        let mut restored = Session::new();
+        assert!(restored.command(&format!(":load {}", path.display())).is_ok());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(restored.symbols, session.symbols);
+        assert_eq!(restored.eval_line("v"), session.eval_line("v"));
+    }
+
+    #[test]
+    fn derivatives_in_unit_names() {
+        let mut session = Session::new();
+        assert_eq!(session.enter("diff(t^2, t)"), Ok(String::from("[1] 2*t")));
+        assert_eq!(session.enter("diff(s^3, s)"), Ok(String::from("[2] 3*s^2")));
+        assert_eq!(session.enter("diff(m^2*s, m)"), Ok(String::from("[3] 2*m*s")));
+        assert_eq!(session.enter("diff(g^2, g)"), Ok(String::from("[4] 2*g")));
+        session.eval_line("t = 3").unwrap();
+        assert_eq!(session.enter("diff(t^2, t)"), Ok(String::from("[5] 6")));
+        assert!(session.command(":symbolic on").is_ok());
+        assert_eq!(session.enter("diff(s^3, s)"), Ok(String::from("[6] 3*s^2")));
+        assert_eq!(session.enter("diff(m^2 + 1, m)"), Ok(String::from("[7] 2*m")));
+        assert_eq!(session.enter("diff(g^3, g) + 1"), Ok(String::from("[8] diff(g^3, g) + 1")));
+    }
+
+    #[test]
+    fn save_formulas() {
+        let path = std::env::temp_dir().join(format!("rustkalkulator-formulas-{}.calc", std::process::id()));
+        let mut session = Session::new();
+        session.command(":symbolic on").unwrap();
+        session.eval_line("y = 2*x + 1").unwrap();
+        session.eval_line("z = y^2").unwrap();
+        session.eval_line("w = 4").unwrap();
+        assert_eq!(session.serialize(), format!("{}\n:symbolic on\nw = 4\ny = 2*x + 1\nz = y^2\n", HEADER));
+        assert_eq!(session.command(&format!(":save {}", path.display())), Ok(format!("Saved 3 variables to {}", path.display())));
+        let mut restored = Session::new();
+        assert!(restored.command(&format!(":load {}", path.display())).is_ok());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(restored.symbols, session.symbols);
+        assert!(restored.settings.symbolic);
+        restored.eval_line("x = 3").unwrap();
+        assert_eq!(restored.eval_line("z"), Ok(Value::Int(49)));
+        assert!(Session::new().restore(&format!("{}\n:load other.calc\n", HEADER)).is_err());
+    }
+
+    #[test]
+    fn save_decimal_mode() {
+        let path = std::env::temp_dir().join(format!("rustkalkulator-decimal-{}.calc", std::process::id()));
+        let mut session = Session::new();
+        session.command(":precision 30").unwrap();
+        session.command(":rounding half-up").unwrap();
+        session.eval_line("x = 1/3").unwrap();
+        assert_eq!(session.serialize(), format!("{}\n:precision 30\n:rounding half-up\nx = 0.{}\n", HEADER, "3".repeat(30)));
+        session.command(&format!(":save {}", path.display())).unwrap();
+        let mut restored = Session::new();
+        assert!(restored.command(&format!(":load {}", path.display())).is_ok());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(restored.settings, session.settings);
+        assert_eq!(restored.symbols, session.symbols);
+        assert_eq!(restored.enter("x * 3"), Ok(format!("[1] 0.{}", "9".repeat(30))));
+    }
+}
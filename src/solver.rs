@@ -0,0 +1,458 @@
+use super::evaluator::{self, Settings};
+use super::parser::ParseTree;
+use super::symbolic;
+use super::value::Value;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+// Numeric root finding looks here unless given a range.
+const RANGE: (f64, f64) = (-100.0, 100.0);
+
+// Points sampled across the range when looking for sign changes.
+const SAMPLES: usize = 2000;
+
+const ITERATIONS: usize = 200;
+
+// Errors that only mean the function has no value at that point, like 1/x
+// at zero. Any other error is a mistake in the equation itself.
+const NO_VALUE: &[&str] = &["Division by zero", "Logarithm of zero", "Result is not a finite number", "Integer overflow", "Decimal result out of range"];
+
+// `solve(lhs = rhs, x)` or `solve(lhs = rhs, x, low, high)`. A bare
+// expression is solved for zero. Polynomials up to degree two are solved
+// exactly; anything else numerically. A list of equations goes to
+// `system` instead.
+pub fn solve(arguments: Vec<ParseTree>, symbols: &mut HashMap<String, Value>, settings: &Settings) -> Result<Value, String> {
+    if let Some(ParseTree::List(_)) = arguments.first() {
+        return system(arguments, symbols);
+    }
+    let usage = || String::from("solve takes an equation and a variable, like solve(x^2 = 2, x), and optionally a range");
+    let mut arguments = arguments.into_iter();
+    let (equation, x) = match (arguments.next(), arguments.next()) {
+        (Some(equation), Some(ParseTree::Var(x))) => (equation, x),
+        _ => return Err(usage())
+    };
+    let (low, high) = match (arguments.next(), arguments.next(), arguments.next()) {
+        (None, None, None) => RANGE,
+        (Some(low), Some(high), None) => (bound(low, symbols, settings)?, bound(high, symbols, settings)?),
+        _ => return Err(usage())
+    };
+    if low >= high {
+        return Err(format!("The range is empty: {} to {}", low, high));
+    }
+    let f = function(equation, std::slice::from_ref(&x), symbols)?;
+    let roots = match symbolic::polynomial(&f, &x) {
+        Some(coefficients) if coefficients.len() <= 3 => exact(&coefficients, &x)?,
+        _ => numeric(&f, &x, symbols, settings, low, high)?
+    };
+    match roots.len() {
+        0 => Err(format!("No real solution for {} between {} and {}", x, low, high)),
+        1 => Ok(roots.into_iter().next().unwrap()),
+        _ => Ok(Value::List(roots))
+    }
+}
+
+// The difference of the two sides, with the values of every variable
+// other than the unknowns written in.
+fn function(equation: ParseTree, unknowns: &[String], symbols: &HashMap<String, Value>) -> Result<ParseTree, String> {
+    let difference = match equation {
+        ParseTree::Equation(left, right) => symbolic::difference(*left, *right),
+        tree => tree
+    };
+    let lookup = |name: &str| match symbols.get(name) {
+        Some(value) if !unknowns.iter().any(|x| x == name) => symbolic::literal(value.clone()),
+        _ => None
+    };
+    let f = symbolic::simplify(symbolic::substitute(difference, &lookup));
+    match symbolic::variables(&f).into_iter().find(|name| !unknowns.contains(name) && !evaluator::is_defined(name, symbols)) {
+        Some(name) => Err(format!("Undefined variable: {}", name)),
+        None => Ok(f)
+    }
+}
+
+fn bound(tree: ParseTree, symbols: &HashMap<String, Value>, settings: &Settings) -> Result<f64, String> {
+    match evaluator::evaluate(Ok(Box::new(tree)), symbols.clone(), settings).0? {
+        x @ Value::Int(_) | x @ Value::Real(_) | x @ Value::Decimal(_) => Ok(x.to_f64()),
+        _ => Err(String::from("The range must be given in real numbers"))
+    }
+}
+
+// Roots of a + bx + cx^2, from the formula.
+fn exact(coefficients: &[Value], x: &str) -> Result<Vec<Value>, String> {
+    match coefficients {
+        [] => Err(format!("Every value of {} solves the equation", x)),
+        [_] => Err(String::from("The equation has no solution")),
+        [b, a] => Ok(vec![match (b, a) {
+            (Value::Int(b), Value::Int(a)) if b % a == 0 => Value::Int(-b / a),
+            _ => Value::Real(-b.to_f64() / a.to_f64())
+        }]),
+        _ => {
+            let (c, b, a) = (coefficients[0].to_f64(), coefficients[1].to_f64(), coefficients[2].to_f64());
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant < 0.0 {
+                return Err(String::from("The equation has no real solutions"));
+            }
+            if discriminant == 0.0 {
+                return Ok(vec![Value::Real(-b / (2.0 * a))]);
+            }
+            // Adding numbers of the same sign avoids cancelling digits away.
+            let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+            let mut roots = [q / a, c / q];
+            roots.sort_by(f64::total_cmp);
+            Ok(roots.iter().map(|root| Value::Real(*root)).collect())
+        }
+    }
+}
+
+// Looks for sign changes between sample points and narrows each one down
+// by bisection before polishing it with Newton's method. Roots where the
+// curve only touches zero show up as dips in its size instead.
+fn numeric(f: &ParseTree, x: &str, symbols: &HashMap<String, Value>, settings: &Settings, low: f64, high: f64) -> Result<Vec<Value>, String> {
+    let at = |tree: &ParseTree, t: f64| {
+        let mut scope = symbols.clone();
+        scope.insert(x.to_string(), Value::Real(t));
+        match evaluator::evaluate(Ok(Box::new(tree.clone())), scope, settings).0 {
+            Ok(y @ Value::Int(_)) | Ok(y @ Value::Real(_)) | Ok(y @ Value::Decimal(_)) => Ok(Some(y.to_f64())),
+            Err(e) if !NO_VALUE.contains(&e.as_str()) => Err(e),
+            _ => Ok(None)
+        }
+    };
+    let slope = symbolic::derivative(f, x).ok();
+    let value = |t: f64| at(f, t).ok().flatten();
+    let newton = |mut t: f64, a: f64, b: f64| {
+        let slope = slope.as_ref()?;
+        for _ in 0..ITERATIONS {
+            let (y, dy) = (value(t)?, at(slope, t).ok().flatten()?);
+            if y == 0.0 || dy == 0.0 {
+                break;
+            }
+            let next = t - y / dy;
+            if !(a..=b).contains(&next) || next == t {
+                break;
+            }
+            t = next;
+        }
+        Some(t)
+    };
+    let step = (high - low) / SAMPLES as f64;
+    let points = (0..=SAMPLES).map(|i| low + i as f64 * step).map(|t| Ok((t, at(f, t)?))).collect::<Result<Vec<(f64, Option<f64>)>, String>>()?;
+    let mut roots = Vec::new();
+    for window in points.windows(2) {
+        if let [(a, Some(fa)), (b, Some(fb))] = *window {
+            if fa == 0.0 {
+                roots.push(a);
+            } else if fb != 0.0 && fa.signum() != fb.signum() {
+                let root = bisect(&value, a, b, fa);
+                let root = newton(root, a, b).unwrap_or(root);
+                // A jump across zero, like 1/x has, is not a root.
+                if value(root).is_some_and(|y| y.abs() <= 1e-8 * fa.abs().max(fb.abs()).max(1.0)) {
+                    roots.push(root);
+                }
+            }
+        }
+    }
+    if let Some((t, Some(y))) = points.last() {
+        if *y == 0.0 {
+            roots.push(*t);
+        }
+    }
+    for window in points.windows(3) {
+        if let [(a, Some(fa)), (t, Some(ft)), (c, Some(fc))] = *window {
+            let dip = ft.abs() < fa.abs() && ft.abs() <= fc.abs() && fa.signum() == ft.signum() && ft.signum() == fc.signum();
+            if let Some(root) = newton(t, a, c).filter(|_| dip) {
+                if value(root).is_some_and(|y| y.abs() <= 1e-12 * fa.abs().max(fc.abs()).max(1.0)) {
+                    roots.push(root);
+                }
+            }
+        }
+    }
+    roots.sort_by(f64::total_cmp);
+    roots.dedup_by(|a, b| (*a - *b).abs() <= 1e-9 * b.abs().max(1.0));
+    Ok(roots.into_iter().map(Value::Real).collect())
+}
+
+fn bisect(value: &impl Fn(f64) -> Option<f64>, mut a: f64, mut b: f64, mut fa: f64) -> f64 {
+    for _ in 0..ITERATIONS {
+        let middle = (a + b) / 2.0;
+        if middle == a || middle == b {
+            break;
+        }
+        match value(middle) {
+            Some(0.0) => return middle,
+            Some(y) if y.signum() == fa.signum() => {
+                a = middle;
+                fa = y;
+            },
+            _ => b = middle
+        }
+    }
+    (a + b) / 2.0
+}
+
+// `solve([2*x + y = 5, x - y = 1], [x, y])` for linear equations, with
+// `bind` as a third argument to also assign the solution.
+fn system(arguments: Vec<ParseTree>, symbols: &mut HashMap<String, Value>) -> Result<Value, String> {
+    let usage = || String::from("solve takes a list of equations and a list of unknowns, like solve([x + y = 3, x - y = 1], [x, y]), and optionally bind");
+    let (equations, unknowns, bind) = match <[ParseTree; 2]>::try_from(arguments) {
+        Ok([ParseTree::List(equations), ParseTree::List(unknowns)]) => (equations, unknowns, false),
+        Ok(_) => return Err(usage()),
+        Err(arguments) => match <[ParseTree; 3]>::try_from(arguments) {
+            Ok([ParseTree::List(equations), ParseTree::List(unknowns), ParseTree::Var(flag)]) if flag == "bind" => (equations, unknowns, true),
+            _ => return Err(usage())
+        }
+    };
+    let unknowns: Vec<String> = unknowns.into_iter().map(|x| match x {
+        ParseTree::Var(name) => Ok(name),
+        _ => Err(usage())
+    }).collect::<Result<_, _>>()?;
+    if equations.is_empty() || unknowns.is_empty() {
+        return Err(usage());
+    }
+    let mut rows = Vec::new();
+    for equation in equations {
+        let f = function(equation, &unknowns, symbols)?;
+        let (mut row, constant) = symbolic::linear(&f, &unknowns)
+            .ok_or_else(|| format!("The equations are not linear in {}", unknowns.join(", ")))?;
+        row.push(constant.neg()?);
+        rows.push(row);
+    }
+    let exact = rows.iter().flatten().map(|x| match x {
+        Value::Int(x) => Some(Ratio(*x, 1)),
+        _ => None
+    }).collect::<Option<Vec<Ratio>>>();
+    let width = unknowns.len() + 1;
+    // Exact fractions while the numbers are all integers and stay small
+    // enough, floating point otherwise.
+    let outcome = exact.and_then(|exact| eliminate(exact.chunks(width).map(|row| row.to_vec()).collect()))
+        .or_else(|| eliminate(rows.iter().map(|row| row.iter().map(|x| x.to_f64()).collect()).collect()))
+        .ok_or_else(|| String::from("The system could not be solved"))?;
+    let solution = match outcome {
+        Outcome::Unique(solution) => solution,
+        Outcome::Inconsistent => return Err(String::from("The system is singular: the equations contradict each other")),
+        Outcome::Underdetermined => return Err(String::from("The system is underdetermined: there are infinitely many solutions"))
+    };
+    if bind {
+        for (name, x) in unknowns.iter().zip(&solution) {
+            symbols.insert(name.clone(), x.clone());
+        }
+    }
+    Ok(Value::List(solution))
+}
+
+enum Outcome {
+    Unique(Vec<Value>),
+    Inconsistent,
+    Underdetermined,
+}
+
+// Numbers that Gaussian elimination can work in. The operations give
+// `None` when an exact number would overflow.
+trait Scalar: Copy {
+    fn is_zero(&self) -> bool;
+    fn size(&self) -> f64;
+    fn sub(self, other: Self) -> Option<Self>;
+    fn mul(self, other: Self) -> Option<Self>;
+    fn div(self, other: Self) -> Option<Self>;
+    fn value(self) -> Value;
+}
+
+impl Scalar for f64 {
+    fn is_zero(&self) -> bool {
+        self.abs() < 1e-12
+    }
+
+    fn size(&self) -> f64 {
+        self.abs()
+    }
+
+    fn sub(self, other: f64) -> Option<f64> {
+        Some(self - other)
+    }
+
+    fn mul(self, other: f64) -> Option<f64> {
+        Some(self * other)
+    }
+
+    fn div(self, other: f64) -> Option<f64> {
+        Some(self / other)
+    }
+
+    fn value(self) -> Value {
+        Value::Real(self)
+    }
+}
+
+// A fraction in lowest terms with a positive denominator.
+#[derive(Clone, Copy)]
+struct Ratio(i128, i128);
+
+impl Ratio {
+    fn new(numerator: i128, denominator: i128) -> Option<Ratio> {
+        let divisor = gcd(numerator, denominator);
+        let sign = if denominator < 0 { -1 } else { 1 };
+        Some(Ratio((numerator / divisor).checked_mul(sign)?, (denominator / divisor).checked_mul(sign)?))
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    match b {
+        0 if a == 0 => 1,
+        0 => a.abs(),
+        b => gcd(b, a % b)
+    }
+}
+
+impl Scalar for Ratio {
+    fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    fn size(&self) -> f64 {
+        (self.0 as f64 / self.1 as f64).abs()
+    }
+
+    fn sub(self, other: Ratio) -> Option<Ratio> {
+        Ratio::new(self.0.checked_mul(other.1)?.checked_sub(other.0.checked_mul(self.1)?)?, self.1.checked_mul(other.1)?)
+    }
+
+    fn mul(self, other: Ratio) -> Option<Ratio> {
+        Ratio::new(self.0.checked_mul(other.0)?, self.1.checked_mul(other.1)?)
+    }
+
+    fn div(self, other: Ratio) -> Option<Ratio> {
+        Ratio::new(self.0.checked_mul(other.1)?, self.1.checked_mul(other.0)?)
+    }
+
+    fn value(self) -> Value {
+        match self {
+            Ratio(n, 1) => Value::Int(n),
+            Ratio(n, d) => Value::Real(n as f64 / d as f64)
+        }
+    }
+}
+
+// Gauss-Jordan elimination on the augmented matrix, with partial pivoting.
+// Rows that end up all zero except for the last column contradict the
+// rest; columns without a pivot are unknowns the equations leave free.
+fn eliminate<T: Scalar>(mut rows: Vec<Vec<T>>) -> Option<Outcome> {
+    let unknowns = rows.first().map_or(0, |row| row.len() - 1);
+    let mut pivots = Vec::new();
+    for column in 0..unknowns {
+        let rank = pivots.len();
+        let pivot = (rank..rows.len())
+            .filter(|i| !rows[*i][column].is_zero())
+            .max_by(|i, j| rows[*i][column].size().total_cmp(&rows[*j][column].size()));
+        let pivot = match pivot {
+            Some(pivot) => pivot,
+            None => continue
+        };
+        rows.swap(rank, pivot);
+        let lead = rows[rank][column];
+        rows[rank] = rows[rank].iter().map(|x| x.div(lead)).collect::<Option<_>>()?;
+        let pivot_row = rows[rank].clone();
+        for (_, row) in rows.iter_mut().enumerate().filter(|(i, _)| *i != rank) {
+            let factor = row[column];
+            if factor.is_zero() {
+                continue;
+            }
+            for (x, p) in row.iter_mut().zip(&pivot_row) {
+                *x = x.sub(factor.mul(*p)?)?;
+            }
+        }
+        pivots.push(column);
+    }
+    if rows[pivots.len()..].iter().any(|row| !row[unknowns].is_zero()) {
+        return Some(Outcome::Inconsistent);
+    }
+    if pivots.len() < unknowns {
+        return Some(Outcome::Underdetermined);
+    }
+    Some(Outcome::Unique(rows.iter().take(unknowns).map(|row| row[unknowns].value()).collect()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lexer::Tokens;
+    use crate::parser::Parser;
+
+    fn solve_line(line: &str) -> Result<Value, String> {
+        solve_with(line, &mut HashMap::new(), &Settings::default())
+    }
+
+    fn solve_with(line: &str, symbols: &mut HashMap<String, Value>, settings: &Settings) -> Result<Value, String> {
+        let mut tokenizer = Tokens::new(line.chars());
+        tokenizer.tokenize();
+        let mut parser = Parser::new();
+        parser.parse(tokenizer.tokens);
+        match *parser.tree.unwrap() {
+            ParseTree::Call(_, arguments) => solve(arguments, symbols, settings),
+            tree => panic!("not a call: {:?}", tree)
+        }
+    }
+
+    fn close(value: Result<Value, String>, expected: &[f64]) -> bool {
+        let roots = match value {
+            Ok(Value::List(roots)) => roots,
+            Ok(root) => vec![root],
+            Err(_) => return false
+        };
+        roots.len() == expected.len() && roots.iter().zip(expected).all(|(x, y)| (x.to_f64() - y).abs() < 1e-9)
+    }
+
+    #[test]
+    fn linear_and_quadratic() {
+        assert_eq!(solve_line("solve(2*x + 3 = 11, x)"), Ok(Value::Int(4)));
+        assert_eq!(solve_line("solve(3*x = 1, x)"), Ok(Value::Real(1.0 / 3.0)));
+        assert_eq!(solve_line("solve(x^2 = 2*x + 3, x)"), Ok(Value::List(vec![Value::Real(-1.0), Value::Real(3.0)])));
+        assert_eq!(solve_line("solve((x - 2)^2, x)"), Ok(Value::Real(2.0)));
+        assert_eq!(solve_line("solve(x^2 + 1 = 0, x)"), Err(String::from("The equation has no real solutions")));
+        assert_eq!(solve_line("solve(x + 1 = x, x)"), Err(String::from("The equation has no solution")));
+        assert_eq!(solve_line("solve(2*x = x + x, x)"), Err(String::from("Every value of x solves the equation")));
+    }
+
+    #[test]
+    fn numeric_roots() {
+        assert!(close(solve_line("solve(x^3 = 2, x)"), &[2f64.cbrt()]));
+        assert!(close(solve_line("solve(exp(x) = 3 - x, x)"), &[0.792059968430677]));
+        assert!(close(solve_line("solve(x^3 - x, x)"), &[-1.0, 0.0, 1.0]));
+        assert!(close(solve_line("solve((x - 1)^2*exp(x), x)"), &[1.0]));
+        assert!(close(solve_line("solve(x^4 = 16, x, 0, 10)"), &[2.0]));
+        assert_eq!(solve_line("solve(1/x, x)"), Err(String::from("No real solution for x between -100 and 100")));
+        assert_eq!(solve_line("solve(x = y, x)"), Err(String::from("Undefined variable: y")));
+        assert!(solve_line("solve(x = 1, 2)").is_err());
+        assert_eq!(solve_line("solve(foo(x) = 1, x)"), Err(String::from("Unknown function: foo")));
+        assert!(close(solve_line("solve(ln(x) = 1, x, 0, 10)"), &[std::f64::consts::E]));
+    }
+
+    #[test]
+    fn known_values() {
+        let mut symbols = HashMap::new();
+        symbols.insert(String::from("a"), Value::Int(3));
+        symbols.insert(String::from("x"), Value::Int(10));
+        let settings = Settings::default();
+        assert_eq!(solve_with("solve(a*x = 12, x)", &mut symbols, &settings), Ok(Value::Int(4)));
+        assert_eq!(solve_with("solve(x^2 = a + 1, x)", &mut symbols, &settings), Ok(Value::List(vec![Value::Real(-2.0), Value::Real(2.0)])));
+        assert!(close(solve_with("solve(x^3 = a*9, x)", &mut symbols, &settings), &[3.0]));
+        assert_eq!(symbols.get("x"), Some(&Value::Int(10)));
+    }
+
+    #[test]
+    fn decimal_bounds() {
+        let settings = Settings { precision: Some(30), ..Settings::default() };
+        assert!(close(solve_with("solve(x^3 = 2, x, 0, 3/2)", &mut HashMap::new(), &settings), &[2f64.cbrt()]));
+        assert_eq!(solve_line("solve(x^3 = 2, x, 0, 3/2)"), Err(String::from("No real solution for x between 0 and 1")));
+    }
+
+    #[test]
+    fn linear_systems() {
+        assert_eq!(solve_line("solve([2*x + y = 5, x - y = 1], [x, y])"), Ok(Value::List(vec![Value::Int(2), Value::Int(1)])));
+        assert_eq!(solve_line("solve([2x + y = 5, x - y = 1], [x, y])"), Ok(Value::List(vec![Value::Int(2), Value::Int(1)])));
+        assert_eq!(solve_line("solve([x + y = 1, x - y = 0], [x, y])"), Ok(Value::List(vec![Value::Real(0.5), Value::Real(0.5)])));
+        assert!(close(solve_line("solve([0.1*x + 0.2*y = 0.5, x = 3*y], [x, y])"), &[3.0, 1.0]));
+        assert_eq!(solve_line("solve([x + y + z = 6, y = 2*z, x = z], [x, y, z])"), Ok(Value::List(vec![Value::Real(1.5), Value::Int(3), Value::Real(1.5)])));
+        assert_eq!(solve_line("solve([x + y = 1, 2*x + 2*y = 3], [x, y])"), Err(String::from("The system is singular: the equations contradict each other")));
+        assert_eq!(solve_line("solve([x + y = 1, 2*x + 2*y = 2], [x, y])"), Err(String::from("The system is underdetermined: there are infinitely many solutions")));
+        assert_eq!(solve_line("solve([x*y = 1, x = y], [x, y])"), Err(String::from("The equations are not linear in x, y")));
+        assert!(solve_line("solve([x = 1], x)").is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use super::value::Value;
+use std::convert::TryFrom;
+
+// The longest list a range may produce.
+const MAX_RANGE: usize = 1_000_000;
+
+// Statistics take one list, or their arguments as the list, so that
+// `max(3, 5)` works as well as `max(data)`. Matrices count every element.
+fn values(arguments: Vec<Value>) -> Vec<Value> {
+    let items = match <[Value; 1]>::try_from(arguments) {
+        Ok([Value::List(items)]) => items,
+        Ok([x]) => vec![x],
+        Err(arguments) => arguments
+    };
+    items.into_iter().flat_map(|x| match x {
+        Value::List(items) => values(vec![Value::List(items)]),
+        x => vec![x]
+    }).collect()
+}
+
+fn nonempty(name: &str, items: Vec<Value>) -> Result<Vec<Value>, String> {
+    match items.is_empty() {
+        true => Err(format!("{} of an empty list", name)),
+        false => Ok(items)
+    }
+}
+
+// Ordering only makes sense for real numbers, and for quantities of one kind.
+fn sorted(name: &str, items: Vec<Value>) -> Result<Vec<Value>, String> {
+    let mut items = nonempty(name, items)?;
+    for x in items.iter() {
+        if let Value::Complex(..) = x {
+            return Err(String::from("Complex numbers have no order"));
+        }
+        items[0].clone().sub(x.clone())?;
+    }
+    items.sort_by(|x, y| x.to_f64().total_cmp(&y.to_f64()));
+    Ok(items)
+}
+
+// Starts from the first item rather than zero, so quantities add up too.
+pub fn sum(arguments: Vec<Value>) -> Result<Value, String> {
+    let mut items = values(arguments).into_iter();
+    match items.next() {
+        Some(first) => items.try_fold(first, Value::add),
+        None => Ok(Value::Int(0))
+    }
+}
+
+pub fn count(arguments: Vec<Value>) -> Result<Value, String> {
+    Ok(Value::Int(values(arguments).len() as i128))
+}
+
+// Averages divide through floating point, unless the caller has a more
+// exact division, as decimal mode does.
+pub type Divide<'a> = &'a dyn Fn(Value, Value) -> Result<Value, String>;
+
+fn real(x: Value, n: Value) -> Result<Value, String> {
+    x.div(Value::Real(n.to_f64()))
+}
+
+pub fn mean(arguments: Vec<Value>) -> Result<Value, String> {
+    mean_by(arguments, &real)
+}
+
+pub fn mean_by(arguments: Vec<Value>, divide: Divide) -> Result<Value, String> {
+    let items = nonempty("mean", values(arguments))?;
+    let n = items.len() as i128;
+    divide(sum(vec![Value::List(items)])?, Value::Int(n))
+}
+
+pub fn median(arguments: Vec<Value>) -> Result<Value, String> {
+    median_by(arguments, &real)
+}
+
+pub fn median_by(arguments: Vec<Value>, divide: Divide) -> Result<Value, String> {
+    let items = sorted("median", values(arguments))?;
+    let middle = items.len() / 2;
+    match items.len() % 2 {
+        1 => Ok(items[middle].clone()),
+        _ => divide(items[middle - 1].clone().add(items[middle].clone())?, Value::Int(2))
+    }
+}
+
+// The most common value; ties go to the smallest.
+pub fn mode(arguments: Vec<Value>) -> Result<Value, String> {
+    let items = sorted("mode", values(arguments))?;
+    let mut best = (0, 0);
+    let mut start = 0;
+    for i in 1..=items.len() {
+        if i == items.len() || items[i] != items[start] {
+            if i - start > best.1 {
+                best = (start, i - start);
+            }
+            start = i;
+        }
+    }
+    Ok(items[best.0].clone())
+}
+
+// The sample variance, dividing by n - 1 like a spreadsheet's VAR.
+pub fn variance(arguments: Vec<Value>) -> Result<Value, String> {
+    variance_by(arguments, &real)
+}
+
+pub fn variance_by(arguments: Vec<Value>, divide: Divide) -> Result<Value, String> {
+    let items = values(arguments);
+    if items.len() < 2 {
+        return Err(String::from("Variance needs at least 2 values"));
+    }
+    let n = items.len() as i128;
+    let average = mean_by(items.clone(), divide)?;
+    let squares = items.into_iter().map(|x| {
+        let deviation = x.sub(average.clone())?;
+        deviation.clone().mul(deviation)
+    }).collect::<Result<_, _>>()?;
+    divide(sum(vec![Value::List(squares)])?, Value::Int(n - 1))
+}
+
+pub fn stdev(arguments: Vec<Value>) -> Result<Value, String> {
+    variance(arguments)?.sqrt()
+}
+
+pub fn min(arguments: Vec<Value>) -> Result<Value, String> {
+    Ok(sorted("min", values(arguments))?.remove(0))
+}
+
+pub fn max(arguments: Vec<Value>) -> Result<Value, String> {
+    Ok(sorted("max", values(arguments))?.pop().unwrap())
+}
+
+// Interpolates between the closest ranks, like a spreadsheet's PERCENTILE.
+pub fn percentile(arguments: Vec<Value>) -> Result<Value, String> {
+    let (list, p) = match <[Value; 2]>::try_from(arguments) {
+        Ok([list, p]) => (list, p.to_f64()),
+        Err(arguments) => return Err(format!("percentile takes 2 arguments, got {}", arguments.len()))
+    };
+    if !(0.0..=100.0).contains(&p) {
+        return Err(format!("Percentile must be between 0 and 100, got {}", p));
+    }
+    let items = sorted("percentile", values(vec![list]))?;
+    let rank = p / 100.0 * (items.len() - 1) as f64;
+    let (lower, fraction) = (rank.floor() as usize, rank.fract());
+    if fraction == 0.0 {
+        return Ok(items[lower].clone());
+    }
+    let step = items[lower + 1].clone().sub(items[lower].clone())?;
+    items[lower].clone().add(step.mul(Value::Real(fraction))?)
+}
+
+// `range(a, b)` and `range(a, b, step)` count from a up to and including b,
+// the same as `a..b`.
+pub fn range(arguments: Vec<Value>) -> Result<Value, String> {
+    match <[Value; 3]>::try_from(arguments) {
+        Ok([start, end, step]) => sequence(start, end, step),
+        Err(arguments) => match <[Value; 2]>::try_from(arguments) {
+            Ok([start, end]) => sequence(start, end, Value::Int(1)),
+            Err(arguments) => Err(format!("range takes 2 or 3 arguments, got {}", arguments.len()))
+        }
+    }
+}
+
+pub fn sequence(start: Value, end: Value, step: Value) -> Result<Value, String> {
+    for x in [&start, &end, &step].iter() {
+        if !matches!(x, Value::Int(_) | Value::Real(_) | Value::Decimal(_)) {
+            return Err(String::from("Ranges need real numbers"));
+        }
+    }
+    if step.is_zero() {
+        return Err(String::from("Range step cannot be zero"));
+    }
+    let steps = ((end.to_f64() - start.to_f64()) / step.to_f64() + 1e-9).floor();
+    if steps >= MAX_RANGE as f64 {
+        return Err(format!("Range is longer than {} elements", MAX_RANGE));
+    }
+    // Each element is worked out from the start, so rounding errors in a
+    // fractional step do not pile up.
+    let length = if steps < 0.0 { 0 } else { steps as i128 + 1 };
+    (0..length).map(|i| start.clone().add(step.clone().mul(Value::Int(i))?)).collect::<Result<_, _>>().map(Value::List)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn list(items: &[i128]) -> Value {
+        Value::List(items.iter().map(|x| Value::Int(*x)).collect())
+    }
+
+    #[test]
+    fn averages() {
+        let data = || vec![list(&[2, 4, 4, 4, 5, 5, 7, 9])];
+        assert_eq!(sum(data()), Ok(Value::Int(40)));
+        assert_eq!(count(data()), Ok(Value::Int(8)));
+        assert_eq!(mean(data()), Ok(Value::Real(5.0)));
+        assert_eq!(median(data()), Ok(Value::Real(4.5)));
+        assert_eq!(median(vec![list(&[3, 1, 2])]), Ok(Value::Int(2)));
+        assert_eq!(mode(data()), Ok(Value::Int(4)));
+        assert_eq!(mode(vec![list(&[3, 1, 3, 1])]), Ok(Value::Int(1)));
+        assert_eq!(mean(vec![list(&[])]), Err(String::from("mean of an empty list")));
+    }
+
+    #[test]
+    fn spread() {
+        let data = || vec![list(&[2, 4, 4, 4, 5, 5, 7, 9])];
+        assert_eq!(variance(data()), Ok(Value::Real(32.0 / 7.0)));
+        assert_eq!(stdev(data()), Ok(Value::Real((32.0f64 / 7.0).sqrt())));
+        assert_eq!(min(data()), Ok(Value::Int(2)));
+        assert_eq!(max(vec![Value::Int(3), Value::Real(5.5), Value::Int(-1)]), Ok(Value::Real(5.5)));
+        assert!(variance(vec![list(&[1])]).is_err());
+        assert!(max(vec![Value::Complex(1.0, 1.0), Value::Int(1)]).is_err());
+    }
+
+    #[test]
+    fn percentiles() {
+        let data = || list(&[15, 20, 35, 40, 50]);
+        assert_eq!(percentile(vec![data(), Value::Int(0)]), Ok(Value::Int(15)));
+        assert_eq!(percentile(vec![data(), Value::Int(50)]), Ok(Value::Int(35)));
+        assert_eq!(percentile(vec![data(), Value::Int(40)]), Ok(Value::Real(29.0)));
+        assert!(percentile(vec![data(), Value::Int(101)]).is_err());
+        assert!(percentile(vec![data()]).is_err());
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(range(vec![Value::Int(1), Value::Int(5)]), Ok(list(&[1, 2, 3, 4, 5])));
+        assert_eq!(range(vec![Value::Int(10), Value::Int(1), Value::Int(-4)]), Ok(list(&[10, 6, 2])));
+        assert_eq!(range(vec![Value::Int(0), Value::Int(1), Value::Real(0.25)]).map(|x| x.to_string()), Ok(String::from("[0.0, 0.25, 0.5, 0.75, 1.0]")));
+        assert_eq!(range(vec![Value::Int(5), Value::Int(1)]), Ok(list(&[])));
+        assert!(range(vec![Value::Int(1), Value::Int(5), Value::Int(0)]).is_err());
+        assert!(range(vec![Value::Int(0), Value::Int(10_000_000)]).is_err());
+    }
+}
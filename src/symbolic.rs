@@ -0,0 +1,518 @@
+use super::parser::{ParseTree, ProdOp, SumOp};
+use super::value::Value;
+use std::cmp::Ordering;
+
+// Expressions as formulas rather than numbers: derivatives, and the
+// rewriting that keeps the trees they produce readable.
+
+// Distributing a product over sums stops at this many terms.
+const MAX_EXPANSION: usize = 64;
+
+pub fn depends(tree: &ParseTree, x: &str) -> bool {
+    variables(tree).iter().any(|name| name == x)
+}
+
+// Every variable name in the tree, in order of appearance.
+pub fn variables(tree: &ParseTree) -> Vec<String> {
+    match tree {
+        ParseTree::Var(name) => vec![name.clone()],
+        ParseTree::Num(_) | ParseTree::Real(_) | ParseTree::Decimal(_) | ParseTree::Imag(_) => Vec::new(),
+        ParseTree::Sum(_, a, b) | ParseTree::Prod(_, a, b) | ParseTree::Bit(_, a, b) | ParseTree::Power(a, b)
+            | ParseTree::Convert(a, b) | ParseTree::Index(a, b) | ParseTree::Range(a, b) | ParseTree::Equation(a, b) => [variables(a), variables(b)].concat(),
+        ParseTree::Unary(_, a) | ParseTree::Not(a) | ParseTree::Factorial(a) | ParseTree::Percent(a) | ParseTree::Assign(_, a) | ParseTree::Define(_, a) | ParseTree::Const(_, a) => variables(a),
+        ParseTree::Call(_, items) | ParseTree::List(items) => items.iter().flat_map(variables).collect()
+    }
+}
+
+// The variables that derivatives in the tree are taken in.
+pub fn differentiated(tree: &ParseTree) -> Vec<String> {
+    match tree {
+        ParseTree::Call(name, items) => {
+            let named = match (name.as_str(), items.get(1)) {
+                ("diff", Some(ParseTree::Var(x))) => vec![x.clone()],
+                _ => Vec::new()
+            };
+            [named, items.iter().flat_map(differentiated).collect()].concat()
+        },
+        ParseTree::Var(_) | ParseTree::Num(_) | ParseTree::Real(_) | ParseTree::Decimal(_) | ParseTree::Imag(_) => Vec::new(),
+        ParseTree::Sum(_, a, b) | ParseTree::Prod(_, a, b) | ParseTree::Bit(_, a, b) | ParseTree::Power(a, b)
+            | ParseTree::Convert(a, b) | ParseTree::Index(a, b) | ParseTree::Range(a, b) | ParseTree::Equation(a, b) => [differentiated(a), differentiated(b)].concat(),
+        ParseTree::Unary(_, a) | ParseTree::Not(a) | ParseTree::Factorial(a) | ParseTree::Percent(a) | ParseTree::Assign(_, a) | ParseTree::Define(_, a) | ParseTree::Const(_, a) => differentiated(a),
+        ParseTree::List(items) => items.iter().flat_map(differentiated).collect()
+    }
+}
+
+// Replaces the variables that `lookup` knows with trees.
+pub fn substitute(tree: ParseTree, lookup: &impl Fn(&str) -> Option<ParseTree>) -> ParseTree {
+    let each = |a: Box<ParseTree>| Box::new(substitute(*a, lookup));
+    let all = |items: Vec<ParseTree>| items.into_iter().map(|item| substitute(item, lookup)).collect();
+    match tree {
+        ParseTree::Var(name) => lookup(&name).unwrap_or(ParseTree::Var(name)),
+        ParseTree::Sum(op, a, b) => ParseTree::Sum(op, each(a), each(b)),
+        ParseTree::Prod(op, a, b) => ParseTree::Prod(op, each(a), each(b)),
+        ParseTree::Bit(op, a, b) => ParseTree::Bit(op, each(a), each(b)),
+        ParseTree::Power(a, b) => ParseTree::Power(each(a), each(b)),
+        ParseTree::Convert(a, b) => ParseTree::Convert(each(a), each(b)),
+        ParseTree::Index(a, b) => ParseTree::Index(each(a), each(b)),
+        ParseTree::Range(a, b) => ParseTree::Range(each(a), each(b)),
+        ParseTree::Equation(a, b) => ParseTree::Equation(each(a), each(b)),
+        ParseTree::Unary(op, a) => ParseTree::Unary(op, each(a)),
+        ParseTree::Not(a) => ParseTree::Not(each(a)),
+        ParseTree::Factorial(a) => ParseTree::Factorial(each(a)),
+        ParseTree::Percent(a) => ParseTree::Percent(each(a)),
+        ParseTree::Assign(name, a) => ParseTree::Assign(name, each(a)),
+        ParseTree::Define(name, a) => ParseTree::Define(name, each(a)),
+        ParseTree::Const(name, a) => ParseTree::Const(name, each(a)),
+        ParseTree::Call(name, items) => ParseTree::Call(name, all(items)),
+        ParseTree::List(items) => ParseTree::List(all(items)),
+        tree => tree
+    }
+}
+
+// The derivative with respect to `x`, simplified. Other variables are
+// treated as constants.
+pub fn derivative(tree: &ParseTree, x: &str) -> Result<ParseTree, String> {
+    Ok(simplify(differentiate(tree, x)?))
+}
+
+// Integer division truncates, so the rules divide by raising to a negative
+// power instead, which always works in real numbers.
+fn differentiate(tree: &ParseTree, x: &str) -> Result<ParseTree, String> {
+    if !depends(tree, x) {
+        return Ok(ParseTree::Num(0));
+    }
+    match tree {
+        ParseTree::Var(_) => Ok(ParseTree::Num(1)),
+        ParseTree::Sum(op, a, b) => Ok(ParseTree::Sum(op.clone(), Box::new(differentiate(a, x)?), Box::new(differentiate(b, x)?))),
+        ParseTree::Unary(op, a) => Ok(ParseTree::Unary(op.clone(), Box::new(differentiate(a, x)?))),
+        ParseTree::Prod(ProdOp::Times, a, b) => Ok(sum(
+            product(differentiate(a, x)?, (**b).clone()),
+            product((**a).clone(), differentiate(b, x)?)
+        )),
+        ParseTree::Prod(ProdOp::Divide, a, b) => Ok(product(
+            difference(product(differentiate(a, x)?, (**b).clone()), product((**a).clone(), differentiate(b, x)?)),
+            reciprocal(power((**b).clone(), ParseTree::Num(2)))
+        )),
+        // d/dx a^n = n a^(n-1) a'
+        ParseTree::Power(a, b) if !depends(b, x) => Ok(product(
+            product((**b).clone(), power((**a).clone(), difference((**b).clone(), ParseTree::Num(1)))),
+            differentiate(a, x)?
+        )),
+        // d/dx a^b = a^b (b' ln a + b a' / a)
+        ParseTree::Power(a, b) => Ok(product(
+            tree.clone(),
+            sum(
+                product(differentiate(b, x)?, call("ln", (**a).clone())),
+                product(product((**b).clone(), differentiate(a, x)?), reciprocal((**a).clone()))
+            )
+        )),
+        ParseTree::Call(name, arguments) if name == "diff" => differentiate(&nested(arguments)?, x),
+        ParseTree::Call(name, arguments) if arguments.len() == 1 => {
+            let a = &arguments[0];
+            let outer = match name.as_str() {
+                "sqrt" => reciprocal(product(ParseTree::Num(2), tree.clone())),
+                "exp" => tree.clone(),
+                "ln" => reciprocal(a.clone()),
+                "log" => reciprocal(product(a.clone(), call("ln", ParseTree::Num(10)))),
+                "sin" => call("cos", a.clone()),
+                "cos" => product(ParseTree::Num(-1), call("sin", a.clone())),
+                "tan" => reciprocal(ParseTree::Power(Box::new(call("cos", a.clone())), Box::new(ParseTree::Num(2)))),
+                "abs" => product(a.clone(), reciprocal(tree.clone())),
+                _ => return Err(format!("Cannot differentiate {}", name))
+            };
+            Ok(product(outer, differentiate(a, x)?))
+        },
+        ParseTree::Call(name, _) => Err(format!("Cannot differentiate {}", name)),
+        _ => Err(format!("Cannot differentiate {}", tree))
+    }
+}
+
+// `diff(diff(f, x), x)` differentiates the inner derivative again.
+pub fn nested(arguments: &[ParseTree]) -> Result<ParseTree, String> {
+    match arguments {
+        [f, ParseTree::Var(x)] => derivative(f, x),
+        _ => Err(String::from("diff takes an expression and a variable, like diff(x^2, x)"))
+    }
+}
+
+// A numeric coefficient times a product of powers. A sum of these is the
+// normal form the simplifier works in.
+#[derive(Clone)]
+struct Term {
+    coefficient: Value,
+    factors: Vec<(ParseTree, ParseTree)>,
+}
+
+impl Term {
+    fn constant(coefficient: Value) -> Term {
+        Term { coefficient, factors: Vec::new() }
+    }
+
+    fn factor(base: ParseTree, exponent: ParseTree) -> Term {
+        Term { coefficient: Value::Int(1), factors: vec![(base, exponent)] }
+    }
+
+    fn negate(self) -> Term {
+        Term { coefficient: arithmetic(self.coefficient, Value::Int(-1), Value::mul, |x, y| x * y), ..self }
+    }
+
+    // Powers of the same base add their exponents, and bases to the power
+    // zero drop out.
+    fn times(self, other: Term) -> Term {
+        let mut factors = self.factors;
+        for (base, exponent) in other.factors {
+            match factors.iter().position(|(b, _)| *b == base) {
+                Some(i) => factors[i].1 = simplify(sum(factors[i].1.clone(), exponent)),
+                None => factors.push((base, exponent))
+            }
+        }
+        factors.retain(|(_, exponent)| !is(exponent, 0));
+        // Plain variables first, then everything else, each alphabetically.
+        factors.sort_by_key(|(base, _)| (!matches!(base, ParseTree::Var(_)), base.to_string()));
+        Term { coefficient: arithmetic(self.coefficient, other.coefficient, Value::mul, |x, y| x * y), factors }
+    }
+
+    fn degree(&self) -> f64 {
+        self.factors.iter().filter_map(|(_, exponent)| number(exponent)).map(|e| e.to_f64()).sum()
+    }
+
+    // The term without its sign, which the enclosing sum writes instead.
+    fn tree(&self) -> (bool, ParseTree) {
+        let negative = self.coefficient.to_f64() < 0.0;
+        let size = if negative { self.coefficient.clone().neg().unwrap_or(Value::Real(-self.coefficient.to_f64())) } else { self.coefficient.clone() };
+        let factors = self.factors.iter().map(|(base, exponent)| match is(exponent, 1) {
+            true => base.clone(),
+            false => power(base.clone(), exponent.clone())
+        });
+        let tree = match (literal(size.clone()), is_value(&size, 1)) {
+            (Some(c), false) => factors.fold(c, product),
+            (c, _) => factors.reduce(product).or(c).unwrap_or(ParseTree::Num(1))
+        };
+        (negative, tree)
+    }
+}
+
+// Rewrites into a canonical sum of terms: numbers folded, like terms
+// collected, products spread over sums, and terms ordered by falling
+// degree. Quotients are kept as they are written, since integer division
+// truncates and `(a/b)*c` is not always `a*c/b`.
+pub fn simplify(tree: ParseTree) -> ParseTree {
+    let mut collected: Vec<Term> = Vec::new();
+    for term in terms(tree) {
+        match collected.iter().position(|t| t.factors == term.factors) {
+            Some(i) => {
+                let c = collected[i].coefficient.clone();
+                collected[i].coefficient = arithmetic(c, term.coefficient, Value::add, |x, y| x + y);
+            },
+            None => collected.push(term)
+        }
+    }
+    collected.retain(|term| !is_value(&term.coefficient, 0));
+    collected.sort_by(|a, b| match b.degree().total_cmp(&a.degree()) {
+        Ordering::Equal => key(a).cmp(&key(b)),
+        order => order
+    });
+    rebuild(&collected)
+}
+
+// A sum of terms back as a tree, with the signs moved into the sum.
+fn rebuild(terms: &[Term]) -> ParseTree {
+    let mut parts = terms.iter().map(Term::tree);
+    let first = match parts.next() {
+        Some((true, tree)) => negate(tree),
+        Some((false, tree)) => tree,
+        None => return ParseTree::Num(0)
+    };
+    parts.fold(first, |total, (negative, tree)| match negative {
+        true => difference(total, tree),
+        false => sum(total, tree)
+    })
+}
+
+// The coefficients of a polynomial in `x`, lowest degree first, when the
+// tree simplifies to one with numbers for coefficients.
+pub fn polynomial(tree: &ParseTree, x: &str) -> Option<Vec<Value>> {
+    let mut coefficients: Vec<Value> = Vec::new();
+    for term in terms(simplify(tree.clone())) {
+        let degree = match term.factors.as_slice() {
+            [] => 0,
+            [(ParseTree::Var(name), exponent)] if name == x => match number(exponent) {
+                Some(Value::Int(k)) if (1..=MAX_EXPANSION as i128).contains(&k) => k as usize,
+                _ => return None
+            },
+            _ => return None
+        };
+        if coefficients.len() <= degree {
+            coefficients.resize(degree + 1, Value::Int(0));
+        }
+        let c = coefficients[degree].clone();
+        coefficients[degree] = arithmetic(c, term.coefficient, Value::add, |x, y| x + y);
+    }
+    while coefficients.last().is_some_and(|c| is_value(c, 0)) {
+        coefficients.pop();
+    }
+    Some(coefficients)
+}
+
+// The coefficient of each unknown and the constant term, when the tree is
+// linear in the unknowns with numbers for coefficients.
+pub fn linear(tree: &ParseTree, unknowns: &[String]) -> Option<(Vec<Value>, Value)> {
+    let mut coefficients = vec![Value::Int(0); unknowns.len()];
+    let mut constant = Value::Int(0);
+    for term in terms(simplify(tree.clone())) {
+        let slot = match term.factors.as_slice() {
+            [] => &mut constant,
+            [(ParseTree::Var(name), exponent)] if is(exponent, 1) => &mut coefficients[unknowns.iter().position(|u| u == name)?],
+            _ => return None
+        };
+        *slot = arithmetic(slot.clone(), term.coefficient, Value::add, |x, y| x + y);
+    }
+    Some((coefficients, constant))
+}
+
+fn key(term: &Term) -> String {
+    term.factors.iter().map(|(base, exponent)| format!("{}^{}", base, exponent)).collect::<Vec<_>>().join("*")
+}
+
+fn terms(tree: ParseTree) -> Vec<Term> {
+    match tree {
+        ParseTree::Num(x) => vec![Term::constant(Value::Int(x))],
+        ParseTree::Real(x) => vec![Term::constant(Value::Real(x))],
+        ParseTree::Decimal(text) => vec![Term::constant(Value::Real(text.parse().unwrap_or(f64::NAN)))],
+        ParseTree::Sum(op, a, b) => {
+            let right = terms(*b);
+            let right: Vec<Term> = match op {
+                SumOp::Plus => right,
+                SumOp::Minus => right.into_iter().map(Term::negate).collect()
+            };
+            [terms(*a), right].concat()
+        },
+        ParseTree::Unary(SumOp::Plus, a) => terms(*a),
+        ParseTree::Unary(SumOp::Minus, a) => terms(*a).into_iter().map(Term::negate).collect(),
+        ParseTree::Prod(ProdOp::Times, a, b) => {
+            let (left, right) = (terms(*a), terms(*b));
+            if left.len() * right.len() > MAX_EXPANSION {
+                return vec![Term::factor(product(rebuild(&left), rebuild(&right)), ParseTree::Num(1))];
+            }
+            left.iter().flat_map(|x| right.iter().map(move |y| x.clone().times(y.clone()))).collect()
+        },
+        ParseTree::Prod(ProdOp::Divide, a, b) => {
+            let (a, b) = (simplify(*a), simplify(*b));
+            let exact = |x: Value, y: Value| match (&x, &y) {
+                (Value::Int(m), Value::Int(n)) if *n == 0 || m % n != 0 => Err(String::new()),
+                _ => x.div(y)
+            };
+            match fold(&a, &b, exact) {
+                Some(folded) => terms(folded),
+                None if is(&b, 1) => terms(a),
+                None if is(&a, 0) && !is(&b, 0) => Vec::new(),
+                None => vec![Term::factor(quotient(a, b), ParseTree::Num(1))]
+            }
+        },
+        ParseTree::Power(a, b) => {
+            let (a, b) = (simplify(*a), simplify(*b));
+            if let Some(folded) = fold(&a, &b, Value::pow) {
+                return terms(folded);
+            }
+            let mut base = terms(a.clone());
+            // Whole powers spread over a single product: (2x)^3 is 8x^3.
+            match (number(&b), base.len()) {
+                (Some(Value::Int(n)), 1) if base[0].coefficient.to_f64().powf(n as f64).is_finite() => {
+                    let term = base.remove(0);
+                    let start = Term::constant(arithmetic(term.coefficient, Value::Int(n), Value::pow, f64::powf));
+                    vec![term.factors.into_iter().fold(start, |total, (base, exponent)| {
+                        total.times(Term::factor(base, simplify(product(exponent, b.clone()))))
+                    })]
+                },
+                _ => vec![Term::factor(a, b)]
+            }
+        },
+        ParseTree::Call(name, arguments) => {
+            vec![Term::factor(ParseTree::Call(name, arguments.into_iter().map(simplify).collect()), ParseTree::Num(1))]
+        },
+        ParseTree::List(items) => vec![Term::factor(ParseTree::List(items.into_iter().map(simplify).collect()), ParseTree::Num(1))],
+        tree => vec![Term::factor(tree, ParseTree::Num(1))]
+    }
+}
+
+// Exact while the numbers stay integers, and floating point once they
+// overflow.
+fn arithmetic(x: Value, y: Value, exact: fn(Value, Value) -> Result<Value, String>, float: fn(f64, f64) -> f64) -> Value {
+    let (a, b) = (x.to_f64(), y.to_f64());
+    exact(x, y).unwrap_or_else(|_| Value::Real(float(a, b)))
+}
+
+// The value of a number written out in the tree, sign included.
+fn number(tree: &ParseTree) -> Option<Value> {
+    match tree {
+        ParseTree::Num(x) => Some(Value::Int(*x)),
+        ParseTree::Real(x) => Some(Value::Real(*x)),
+        ParseTree::Decimal(text) => text.parse().ok().map(Value::Real),
+        ParseTree::Unary(SumOp::Minus, a) => number(a)?.neg().ok(),
+        _ => None
+    }
+}
+
+pub fn literal(value: Value) -> Option<ParseTree> {
+    match value {
+        Value::Int(x) if x < 0 => Some(negate(ParseTree::Num(x.checked_neg()?))),
+        Value::Int(x) => Some(ParseTree::Num(x)),
+        Value::Real(x) if !x.is_finite() => None,
+        Value::Real(x) if x < 0.0 => Some(negate(ParseTree::Real(-x))),
+        Value::Real(x) => Some(ParseTree::Real(x)),
+        _ => None
+    }
+}
+
+// Works out an operation on two numbers, unless that would fail or leave
+// the real line.
+fn fold(a: &ParseTree, b: &ParseTree, f: impl Fn(Value, Value) -> Result<Value, String>) -> Option<ParseTree> {
+    literal(f(number(a)?, number(b)?).ok()?)
+}
+
+fn is(tree: &ParseTree, n: i128) -> bool {
+    match number(tree) {
+        Some(x) => is_value(&x, n),
+        None => false
+    }
+}
+
+fn is_value(x: &Value, n: i128) -> bool {
+    matches!(x, Value::Int(_) | Value::Real(_)) && x.to_f64() == n as f64
+}
+
+fn negate(a: ParseTree) -> ParseTree {
+    match a {
+        ParseTree::Unary(SumOp::Minus, a) => *a,
+        ParseTree::Num(0) => a,
+        a => ParseTree::Unary(SumOp::Minus, Box::new(a))
+    }
+}
+
+fn sum(a: ParseTree, b: ParseTree) -> ParseTree {
+    ParseTree::Sum(SumOp::Plus, Box::new(a), Box::new(b))
+}
+
+pub fn difference(a: ParseTree, b: ParseTree) -> ParseTree {
+    ParseTree::Sum(SumOp::Minus, Box::new(a), Box::new(b))
+}
+
+fn product(a: ParseTree, b: ParseTree) -> ParseTree {
+    ParseTree::Prod(ProdOp::Times, Box::new(a), Box::new(b))
+}
+
+fn quotient(a: ParseTree, b: ParseTree) -> ParseTree {
+    ParseTree::Prod(ProdOp::Divide, Box::new(a), Box::new(b))
+}
+
+fn power(a: ParseTree, b: ParseTree) -> ParseTree {
+    ParseTree::Power(Box::new(a), Box::new(b))
+}
+
+fn reciprocal(a: ParseTree) -> ParseTree {
+    power(a, negate(ParseTree::Num(1)))
+}
+
+fn call(name: &str, a: ParseTree) -> ParseTree {
+    ParseTree::Call(String::from(name), vec![a])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::evaluator::{self, Settings};
+    use crate::lexer::Tokens;
+    use crate::parser::Parser;
+    use std::collections::HashMap;
+
+    fn parse(line: &str) -> ParseTree {
+        let mut tokenizer = Tokens::new(line.chars());
+        tokenizer.tokenize();
+        let mut parser = Parser::new();
+        parser.parse(tokenizer.tokens);
+        *parser.tree.unwrap()
+    }
+
+    fn diff(line: &str) -> Result<String, String> {
+        derivative(&parse(line), "x").map(|tree| tree.to_string())
+    }
+
+    #[test]
+    fn derivatives() {
+        assert_eq!(diff("x^3"), Ok(String::from("3*x^2")));
+        assert_eq!(diff("5*x + 7"), Ok(String::from("5")));
+        assert_eq!(diff("a*x^2"), Ok(String::from("2*a*x")));
+        assert_eq!(diff("1/x"), Ok(String::from("-x^-2")));
+        assert_eq!(diff("exp(2*x)"), Ok(String::from("2*exp(2*x)")));
+        assert_eq!(diff("ln(x^2 + 1)"), Ok(String::from("2*x*(x^2 + 1)^-1")));
+        assert_eq!(diff("x*sqrt(x)"), Ok(String::from("sqrt(x) + 0.5*x*sqrt(x)^-1")));
+        assert_eq!(diff("2^x"), Ok(String::from("2^x*ln(2)")));
+        assert_eq!(diff("diff(x^3, x)"), Ok(String::from("6*x")));
+        assert_eq!(diff("y"), Ok(String::from("0")));
+        assert_eq!(diff("sin(2*x)"), Ok(String::from("2*cos(2*x)")));
+        assert_eq!(diff("cos(x)"), Ok(String::from("-sin(x)")));
+        assert_eq!(diff("tan(x)"), Ok(String::from("cos(x)^-2")));
+        assert_eq!(diff("log(x)"), Ok(String::from("x^-1*ln(10)^-1")));
+        assert_eq!(diff("arg(x)"), Err(String::from("Cannot differentiate arg")));
+    }
+
+    #[test]
+    fn simplification() {
+        let simplified = |line| simplify(parse(line)).to_string();
+        assert_eq!(simplified("0 + x*1"), "x");
+        assert_eq!(simplified("2*3 + x^0"), "7");
+        assert_eq!(simplified("x - x"), "0");
+        assert_eq!(simplified("1/2 + 6/3"), "1/2 + 2");
+        assert_eq!(simplified("-(-x)"), "x");
+        assert_eq!(simplified("x*-2"), "-2*x");
+        assert_eq!(simplified("3*(2*x)"), "6*x");
+    }
+
+    #[test]
+    fn like_terms() {
+        let simplified = |line| simplify(parse(line)).to_string();
+        assert_eq!(simplified("2 x + 3 x"), "5*x");
+        assert_eq!(simplified("2x + 3x"), "5*x");
+        assert_eq!(simplified("2x^2 - x^2"), "x^2");
+        assert_eq!(simplified("y*x + 1 + x*y"), "2*x*y + 1");
+        assert_eq!(simplified("x*x*x - x^3 + x^2"), "x^2");
+        assert_eq!(simplified("(x + 1)*(x - 1)"), "x^2 - 1");
+        assert_eq!(simplified("(2*x)^3"), "8*x^3");
+        assert_eq!(simplified("4 - x + 2*x^2"), "2*x^2 - x + 4");
+        assert_eq!(simplified("sqrt(x + x) + (x + 1)/2"), "(x + 1)/2 + sqrt(2*x)");
+    }
+
+    // A tiny linear congruential generator, so the bindings are random but
+    // the test repeats exactly.
+    fn random(seed: &mut u64) -> f64 {
+        *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (*seed >> 11) as f64 / (1u64 << 53) as f64 * 6.0 - 3.0
+    }
+
+    #[test]
+    fn simplified_values_agree() {
+        let formulas = [
+            "2*x + 3*x - y",
+            "(x + y)*(x - y) - x^2",
+            "x*(y + 1)*2 + 3*y*x",
+            "(x*y)^2/(z + 4) + -(-z)",
+            "exp(x - x + y)*x^0 + 0*z",
+            "(x - 1)^3 + 3*x*(x - 1) + 1.5*x",
+            "sqrt(x^2 + y^2)*x/x^1",
+        ];
+        let mut seed = 7;
+        for formula in formulas.iter() {
+            let original = parse(formula);
+            let simplified = simplify(original.clone());
+            for _ in 0..20 {
+                let mut symbols = HashMap::new();
+                for name in ["x", "y", "z"].iter() {
+                    symbols.insert(name.to_string(), Value::Real(random(&mut seed)));
+                }
+                let value = |tree: &ParseTree| evaluator::evaluate(Ok(Box::new(tree.clone())), symbols.clone(), &Settings::default()).0.unwrap();
+                let (expected, actual) = (value(&original).to_f64(), value(&simplified).to_f64());
+                assert!((expected - actual).abs() <= 1e-9 * expected.abs().max(1.0), "{} became {}", formula, simplified);
+            }
+        }
+    }
+}
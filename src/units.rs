@@ -0,0 +1,222 @@
+use std::fmt;
+
+// Exponents of the SI base units, in this order.
+const BASE: &[&str] = &["m", "kg", "s", "A", "K", "mol", "cd"];
+
+#[derive(PartialEq, Debug, Default, Copy, Clone)]
+pub struct Dimension(pub [i8; 7]);
+
+const LENGTH: Dimension = Dimension([1, 0, 0, 0, 0, 0, 0]);
+const MASS: Dimension = Dimension([0, 1, 0, 0, 0, 0, 0]);
+const TIME: Dimension = Dimension([0, 0, 1, 0, 0, 0, 0]);
+const CURRENT: Dimension = Dimension([0, 0, 0, 1, 0, 0, 0]);
+const TEMPERATURE: Dimension = Dimension([0, 0, 0, 0, 1, 0, 0]);
+const AMOUNT: Dimension = Dimension([0, 0, 0, 0, 0, 1, 0]);
+const LUMINOSITY: Dimension = Dimension([0, 0, 0, 0, 0, 0, 1]);
+const AREA: Dimension = Dimension([2, 0, 0, 0, 0, 0, 0]);
+const VOLUME: Dimension = Dimension([3, 0, 0, 0, 0, 0, 0]);
+const SPEED: Dimension = Dimension([1, 0, -1, 0, 0, 0, 0]);
+const FREQUENCY: Dimension = Dimension([0, 0, -1, 0, 0, 0, 0]);
+const FORCE: Dimension = Dimension([1, 1, -2, 0, 0, 0, 0]);
+const ENERGY: Dimension = Dimension([2, 1, -2, 0, 0, 0, 0]);
+const POWER: Dimension = Dimension([2, 1, -3, 0, 0, 0, 0]);
+const PRESSURE: Dimension = Dimension([-1, 1, -2, 0, 0, 0, 0]);
+const CHARGE: Dimension = Dimension([0, 0, 1, 1, 0, 0, 0]);
+const VOLTAGE: Dimension = Dimension([2, 1, -3, -1, 0, 0, 0]);
+
+// Every unit with its size in coherent SI units.
+const UNITS: &[(&str, f64, Dimension)] = &[
+    ("m", 1.0, LENGTH),
+    ("km", 1e3, LENGTH),
+    ("cm", 1e-2, LENGTH),
+    ("mm", 1e-3, LENGTH),
+    ("um", 1e-6, LENGTH),
+    ("nm", 1e-9, LENGTH),
+    ("in", 0.0254, LENGTH),
+    ("ft", 0.3048, LENGTH),
+    ("yd", 0.9144, LENGTH),
+    ("mi", 1609.344, LENGTH),
+    ("nmi", 1852.0, LENGTH),
+    ("kg", 1.0, MASS),
+    ("g", 1e-3, MASS),
+    ("mg", 1e-6, MASS),
+    ("t", 1e3, MASS),
+    ("lb", 0.45359237, MASS),
+    ("oz", 0.028349523125, MASS),
+    ("s", 1.0, TIME),
+    ("ms", 1e-3, TIME),
+    ("us", 1e-6, TIME),
+    ("ns", 1e-9, TIME),
+    ("min", 60.0, TIME),
+    ("h", 3600.0, TIME),
+    ("day", 86400.0, TIME),
+    ("week", 604800.0, TIME),
+    ("yr", 31557600.0, TIME),
+    ("A", 1.0, CURRENT),
+    ("mA", 1e-3, CURRENT),
+    ("K", 1.0, TEMPERATURE),
+    ("mol", 1.0, AMOUNT),
+    ("cd", 1.0, LUMINOSITY),
+    ("ha", 1e4, AREA),
+    ("acre", 4046.8564224, AREA),
+    ("L", 1e-3, VOLUME),
+    ("mL", 1e-6, VOLUME),
+    ("gal", 3.785411784e-3, VOLUME),
+    ("mph", 0.44704, SPEED),
+    ("knot", 1852.0 / 3600.0, SPEED),
+    ("Hz", 1.0, FREQUENCY),
+    ("kHz", 1e3, FREQUENCY),
+    ("MHz", 1e6, FREQUENCY),
+    ("GHz", 1e9, FREQUENCY),
+    ("N", 1.0, FORCE),
+    ("kN", 1e3, FORCE),
+    ("lbf", 4.4482216152605, FORCE),
+    ("J", 1.0, ENERGY),
+    ("kJ", 1e3, ENERGY),
+    ("cal", 4.184, ENERGY),
+    ("kcal", 4184.0, ENERGY),
+    ("Wh", 3600.0, ENERGY),
+    ("kWh", 3.6e6, ENERGY),
+    ("eV", 1.602176634e-19, ENERGY),
+    ("BTU", 1055.05585262, ENERGY),
+    ("W", 1.0, POWER),
+    ("kW", 1e3, POWER),
+    ("MW", 1e6, POWER),
+    ("hp", 745.69987158227, POWER),
+    ("Pa", 1.0, PRESSURE),
+    ("kPa", 1e3, PRESSURE),
+    ("bar", 1e5, PRESSURE),
+    ("atm", 101325.0, PRESSURE),
+    ("psi", 6894.757293168, PRESSURE),
+    ("C", 1.0, CHARGE),
+    ("V", 1.0, VOLTAGE),
+];
+
+// Results are shown in these names when the dimension matches exactly.
+const DERIVED: &[(&str, Dimension)] = &[
+    ("Hz", FREQUENCY),
+    ("N", FORCE),
+    ("J", ENERGY),
+    ("W", POWER),
+    ("Pa", PRESSURE),
+    ("C", CHARGE),
+    ("V", VOLTAGE),
+];
+
+pub fn lookup(name: &str) -> Option<(f64, Dimension)> {
+    UNITS.iter().find(|(n, _, _)| *n == name).map(|(_, size, dimension)| (*size, *dimension))
+}
+
+pub fn names() -> Vec<&'static str> {
+    UNITS.iter().map(|(name, _, _)| *name).collect()
+}
+
+impl Dimension {
+    pub fn is_none(&self) -> bool {
+        self.0.iter().all(|e| *e == 0)
+    }
+
+    pub fn times(self, other: Dimension) -> Result<Dimension, String> {
+        self.combine(other, |a, b| a.checked_add(b))
+    }
+
+    pub fn over(self, other: Dimension) -> Result<Dimension, String> {
+        self.combine(other, |a, b| a.checked_sub(b))
+    }
+
+    // Fractional powers are fine as long as every exponent stays whole,
+    // so the square root of an area is a length.
+    pub fn power(self, n: f64) -> Result<Dimension, String> {
+        let mut exponents = self.0;
+        for e in exponents.iter_mut() {
+            let scaled = *e as f64 * n;
+            if scaled.fract() != 0.0 {
+                return Err(format!("Cannot raise {} to the power {}", self, n));
+            }
+            if scaled.abs() > i8::MAX as f64 {
+                return Err(String::from("Unit exponent out of range"));
+            }
+            *e = scaled as i8;
+        }
+        Ok(Dimension(exponents))
+    }
+
+    // For error messages, where an empty unit would read oddly.
+    pub fn describe(&self) -> String {
+        if self.is_none() {
+            String::from("a plain number")
+        } else {
+            self.to_string()
+        }
+    }
+
+    fn combine(self, other: Dimension, f: impl Fn(i8, i8) -> Option<i8>) -> Result<Dimension, String> {
+        let mut exponents = self.0;
+        for (e, o) in exponents.iter_mut().zip(other.0.iter()) {
+            *e = f(*e, *o).ok_or_else(|| String::from("Unit exponent out of range"))?;
+        }
+        Ok(Dimension(exponents))
+    }
+}
+
+// Written so that it reads back in as the same unit: `kg*m^2/s^2`, and
+// `s^-1` style when nothing is left above the line.
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((name, _)) = DERIVED.iter().find(|(_, d)| d == self) {
+            return write!(f, "{}", name);
+        }
+        let power = |name: &str, e: i8| match e {
+            1 => name.to_string(),
+            e => format!("{}^{}", name, e)
+        };
+        let above: Vec<String> = BASE.iter().zip(self.0.iter())
+            .filter(|(_, e)| **e > 0)
+            .map(|(name, e)| power(name, *e))
+            .collect();
+        if above.is_empty() {
+            let below: Vec<String> = BASE.iter().zip(self.0.iter())
+                .filter(|(_, e)| **e < 0)
+                .map(|(name, e)| power(name, *e))
+                .collect();
+            return write!(f, "{}", below.join("*"));
+        }
+        write!(f, "{}", above.join("*"))?;
+        for (name, e) in BASE.iter().zip(self.0.iter()).filter(|(_, e)| **e < 0) {
+            write!(f, "/{}", power(name, -e))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unit_lookup() {
+        assert_eq!(lookup("km"), Some((1000.0, LENGTH)));
+        assert_eq!(lookup("h"), Some((3600.0, TIME)));
+        assert_eq!(lookup("furlong"), None);
+        assert!(names().contains(&"psi"));
+    }
+
+    #[test]
+    fn dimension_arithmetic() {
+        assert_eq!(LENGTH.over(TIME), Ok(SPEED));
+        assert_eq!(SPEED.times(TIME), Ok(LENGTH));
+        assert_eq!(AREA.power(0.5), Ok(LENGTH));
+        assert!(LENGTH.power(0.5).is_err());
+        assert!(LENGTH.power(200.0).is_err());
+    }
+
+    #[test]
+    fn dimension_display() {
+        assert_eq!(LENGTH.to_string(), "m");
+        assert_eq!(SPEED.to_string(), "m/s");
+        assert_eq!(FORCE.to_string(), "N");
+        assert_eq!(Dimension([2, 1, -2, 0, -1, 0, 0]).to_string(), "m^2*kg/s^2/K");
+        assert_eq!(Dimension([-1, 0, 0, 0, 0, 0, 0]).to_string(), "m^-1");
+        assert_eq!(Dimension::default().describe(), "a plain number");
+    }
+}
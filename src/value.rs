@@ -0,0 +1,596 @@
+use super::decimal::{self, Decimal};
+use super::parser::ParseTree;
+use super::units::Dimension;
+use std::convert::TryFrom;
+use std::fmt;
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Value {
+    Int(i128),
+    Real(f64),
+    // A magnitude in coherent SI units, so `3 km` is `Quantity(3000.0, m)`.
+    Quantity(f64, Dimension),
+    Complex(f64, f64),
+    // An exact decimal, made by literals in decimal mode.
+    Decimal(Decimal),
+    // Vectors are lists of numbers, matrices are lists of rows.
+    List(Vec<Value>),
+    // An expression in variables that have no value yet, like a derivative.
+    Formula(Box<ParseTree>),
+}
+
+// A fixed-width machine integer, used by programmer mode.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Width {
+    pub bits: u32,
+    pub signed: bool,
+}
+
+impl Width {
+    pub fn parse(name: &str) -> Option<Width> {
+        let signed = match name.chars().next() {
+            Some('i') => true,
+            Some('u') => false,
+            _ => return None
+        };
+        match name[1..].parse::<u32>() {
+            Ok(bits) if [8, 16, 32, 64].contains(&bits) => Some(Width { bits, signed }),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> String {
+        format!("{}{}", if self.signed { 'i' } else { 'u' }, self.bits)
+    }
+
+    pub fn mask(&self) -> u128 {
+        (1u128 << self.bits) - 1
+    }
+
+    pub fn wrap(&self, x: i128) -> i128 {
+        let bits = (x as u128) & self.mask();
+        if self.signed && bits >> (self.bits - 1) == 1 {
+            bits as i128 - (1i128 << self.bits)
+        } else {
+            bits as i128
+        }
+    }
+}
+
+impl Value {
+    pub fn add(self, other: Value) -> Result<Value, String> {
+        if let Some((a, b)) = decimal::pair(&self, &other) {
+            return Ok(Value::Decimal(a.add(&b)));
+        }
+        match (&self, &other) {
+            (Value::Formula(_), _) | (_, Value::Formula(_)) => Err(unevaluated()),
+            (Value::List(_), _) | (_, Value::List(_)) => self.elementwise(other, Value::add),
+            (Value::Int(x), Value::Int(y)) => x.checked_add(*y).map(Value::Int).ok_or_else(overflow),
+            (Value::Complex(..), _) | (_, Value::Complex(..)) => {
+                let ((a, b), (c, d)) = (self.rect()?, other.rect()?);
+                complex(a + c, b + d)
+            },
+            (Value::Quantity(..), _) | (_, Value::Quantity(..)) => {
+                let ((x, a), (y, b)) = (self.parts(), other.parts());
+                if a != b {
+                    return Err(format!("Cannot add {} and {}", a.describe(), b.describe()));
+                }
+                quantity(x + y, a)
+            },
+            (x, y) => real(x.to_f64() + y.to_f64())
+        }
+    }
+
+    pub fn sub(self, other: Value) -> Result<Value, String> {
+        if let Some((a, b)) = decimal::pair(&self, &other) {
+            return Ok(Value::Decimal(a.sub(&b)));
+        }
+        match (&self, &other) {
+            (Value::Formula(_), _) | (_, Value::Formula(_)) => Err(unevaluated()),
+            (Value::List(_), _) | (_, Value::List(_)) => self.elementwise(other, Value::sub),
+            (Value::Int(x), Value::Int(y)) => x.checked_sub(*y).map(Value::Int).ok_or_else(overflow),
+            (Value::Complex(..), _) | (_, Value::Complex(..)) => {
+                let ((a, b), (c, d)) = (self.rect()?, other.rect()?);
+                complex(a - c, b - d)
+            },
+            (Value::Quantity(..), _) | (_, Value::Quantity(..)) => {
+                let ((x, a), (y, b)) = (self.parts(), other.parts());
+                if a != b {
+                    return Err(format!("Cannot subtract {} from {}", b.describe(), a.describe()));
+                }
+                quantity(x - y, a)
+            },
+            (x, y) => real(x.to_f64() - y.to_f64())
+        }
+    }
+
+    pub fn mul(self, other: Value) -> Result<Value, String> {
+        if let Some((a, b)) = decimal::pair(&self, &other) {
+            return Ok(Value::Decimal(a.mul(&b)));
+        }
+        match (&self, &other) {
+            (Value::Formula(_), _) | (_, Value::Formula(_)) => Err(unevaluated()),
+            (Value::List(_), _) | (_, Value::List(_)) => self.elementwise(other, Value::mul),
+            (Value::Int(x), Value::Int(y)) => x.checked_mul(*y).map(Value::Int).ok_or_else(overflow),
+            (Value::Complex(..), _) | (_, Value::Complex(..)) => {
+                let ((a, b), (c, d)) = (self.rect()?, other.rect()?);
+                complex(a * c - b * d, a * d + b * c)
+            },
+            (Value::Quantity(..), _) | (_, Value::Quantity(..)) => {
+                let ((x, a), (y, b)) = (self.parts(), other.parts());
+                quantity(x * y, a.times(b)?)
+            },
+            (x, y) => real(x.to_f64() * y.to_f64())
+        }
+    }
+
+    pub fn div(self, other: Value) -> Result<Value, String> {
+        match (&self, &other) {
+            (Value::Formula(_), _) | (_, Value::Formula(_)) => return Err(unevaluated()),
+            (Value::List(_), _) | (_, Value::List(_)) => return self.elementwise(other, Value::div),
+            _ if other.is_zero() => return Err(String::from("Division by zero")),
+            _ => {}
+        }
+        match (&self, &other) {
+            (Value::Int(x), Value::Int(y)) => x.checked_div(*y).map(Value::Int).ok_or_else(overflow),
+            (Value::Complex(..), _) | (_, Value::Complex(..)) => {
+                let ((a, b), (c, d)) = (self.rect()?, other.rect()?);
+                let norm = c * c + d * d;
+                complex((a * c + b * d) / norm, (b * c - a * d) / norm)
+            },
+            (Value::Quantity(..), _) | (_, Value::Quantity(..)) => {
+                let ((x, a), (y, b)) = (self.parts(), other.parts());
+                quantity(x / y, a.over(b)?)
+            },
+            (x, y) => real(x.to_f64() / y.to_f64())
+        }
+    }
+
+    pub fn pow(self, other: Value) -> Result<Value, String> {
+        match (&self, &other) {
+            (Value::Formula(_), _) | (_, Value::Formula(_)) => Err(unevaluated()),
+            (Value::List(_), _) | (_, Value::List(_)) => self.elementwise(other, Value::pow),
+            (_, Value::Quantity(_, unit)) => Err(format!("Exponents cannot have units, got {}", unit)),
+            (Value::Int(x), Value::Int(y)) if *y >= 0 => match (*x, u32::try_from(*y)) {
+                (x, Ok(n)) => x.checked_pow(n).map(Value::Int).ok_or_else(overflow),
+                (-1, Err(_)) => Ok(Value::Int(if y % 2 == 0 { 1 } else { -1 })),
+                (x, Err(_)) if x == 0 || x == 1 => Ok(Value::Int(x)),
+                _ => Err(overflow())
+            },
+            (Value::Quantity(_, unit), Value::Complex(..)) => Err(format!("Cannot raise {} to a complex power", unit)),
+            (Value::Quantity(x, unit), y) => quantity(x.powf(y.to_f64()), unit.power(y.to_f64())?),
+            (Value::Complex(..), _) | (_, Value::Complex(..)) => self.complex_pow(other),
+            // Roots of negative numbers have no real answer, only a complex one.
+            (x, y) if x.to_f64() < 0.0 && y.to_f64().fract() != 0.0 => self.complex_pow(other),
+            (x, y) => real(x.to_f64().powf(y.to_f64()))
+        }
+    }
+
+    fn complex_pow(self, other: Value) -> Result<Value, String> {
+        // Small whole powers multiply out exactly, so that `i^2` is -1.
+        if let Value::Int(n) = other {
+            if n.abs() <= 64 {
+                let mut result = Value::Int(1);
+                for _ in 0..n.abs() {
+                    result = result.mul(self.clone())?;
+                }
+                return if n < 0 { Value::Int(1).div(result) } else { Ok(result) };
+            }
+        }
+        if self.is_zero() {
+            return match other.rect()? {
+                (re, _) if re > 0.0 => Ok(Value::Int(0)),
+                _ => Err(String::from("Division by zero"))
+            };
+        }
+        let ((r, theta), (c, d)) = (self.polar()?, other.rect()?);
+        let (ln_r, angle) = (r.ln(), theta);
+        // (c + di)(ln r + iθ) is the logarithm of the result.
+        let (re, im) = (c * ln_r - d * angle, c * angle + d * ln_r);
+        complex(re.exp() * im.cos(), re.exp() * im.sin())
+    }
+
+    // Lists combine element by element, and a single value on either side
+    // goes with every element of the other.
+    fn elementwise(self, other: Value, f: fn(Value, Value) -> Result<Value, String>) -> Result<Value, String> {
+        match (self, other) {
+            (Value::List(a), Value::List(b)) if a.len() != b.len() => {
+                Err(format!("Lists have different lengths: {} and {}", a.len(), b.len()))
+            },
+            (Value::List(a), Value::List(b)) => a.into_iter().zip(b).map(|(x, y)| f(x, y)).collect::<Result<_, _>>().map(Value::List),
+            (Value::List(a), y) => a.into_iter().map(|x| f(x, y.clone())).collect::<Result<_, _>>().map(Value::List),
+            (x, Value::List(b)) => b.into_iter().map(|y| f(x.clone(), y)).collect::<Result<_, _>>().map(Value::List),
+            (x, y) => f(x, y)
+        }
+    }
+
+    pub fn sqrt(self) -> Result<Value, String> {
+        match self {
+            Value::Quantity(..) => self.pow(Value::Real(0.5)),
+            Value::Int(_) | Value::Real(_) | Value::Decimal(_) if self.to_f64() >= 0.0 => real(self.to_f64().sqrt()),
+            Value::Int(_) | Value::Real(_) | Value::Decimal(_) => complex(0.0, (-self.to_f64()).sqrt()),
+            x => {
+                let (r, theta) = x.polar()?;
+                complex(r.sqrt() * (theta / 2.0).cos(), r.sqrt() * (theta / 2.0).sin())
+            }
+        }
+    }
+
+    pub fn abs(self) -> Result<Value, String> {
+        match self {
+            Value::Int(x) => x.checked_abs().map(Value::Int).ok_or_else(overflow),
+            Value::Real(x) => Ok(Value::Real(x.abs())),
+            Value::Decimal(x) => Ok(Value::Decimal(x.abs())),
+            Value::Quantity(x, unit) => Ok(Value::Quantity(x.abs(), unit)),
+            Value::Complex(re, im) => real(re.hypot(im)),
+            Value::List(_) => Err(not_a_number()),
+            Value::Formula(_) => Err(unevaluated())
+        }
+    }
+
+    pub fn arg(self) -> Result<Value, String> {
+        self.polar().map(|(_, theta)| Value::Real(theta))
+    }
+
+    pub fn conj(self) -> Result<Value, String> {
+        match self {
+            Value::Complex(re, im) => complex(re, -im),
+            x => Ok(x)
+        }
+    }
+
+    pub fn re(self) -> Result<Value, String> {
+        match self {
+            Value::Complex(re, _) => Ok(Value::Real(re)),
+            x => Ok(x)
+        }
+    }
+
+    pub fn im(self) -> Result<Value, String> {
+        match self {
+            Value::Complex(_, im) => Ok(Value::Real(im)),
+            Value::Quantity(_, unit) => Ok(Value::Quantity(0.0, unit)),
+            _ => Ok(Value::Int(0))
+        }
+    }
+
+    pub fn exp(self) -> Result<Value, String> {
+        let (re, im) = self.plain("exp")?;
+        complex(re.exp() * im.cos(), re.exp() * im.sin())
+    }
+
+    pub fn ln(self) -> Result<Value, String> {
+        self.plain("ln")?;
+        if self.is_zero() {
+            return Err(String::from("Logarithm of zero"));
+        }
+        let (r, theta) = self.polar()?;
+        complex(r.ln(), theta)
+    }
+
+    // Logarithms to base 10.
+    pub fn log(self) -> Result<Value, String> {
+        self.plain("log")?;
+        self.ln()?.div(Value::Real(std::f64::consts::LN_10))
+    }
+
+    // Trigonometric functions take radians, and complex numbers too.
+    pub fn sin(self) -> Result<Value, String> {
+        let (re, im) = self.plain("sin")?;
+        complex(re.sin() * im.cosh(), re.cos() * im.sinh())
+    }
+
+    pub fn cos(self) -> Result<Value, String> {
+        let (re, im) = self.plain("cos")?;
+        complex(re.cos() * im.cosh(), -re.sin() * im.sinh())
+    }
+
+    pub fn tan(self) -> Result<Value, String> {
+        self.clone().sin()?.div(self.cos()?)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Value::Complex(re, im) => *re == 0.0 && *im == 0.0,
+            Value::List(_) | Value::Formula(_) => false,
+            x => x.to_f64() == 0.0
+        }
+    }
+
+    // Real and imaginary parts, for anything without a unit.
+    pub fn rect(&self) -> Result<(f64, f64), String> {
+        match self {
+            Value::Complex(re, im) => Ok((*re, *im)),
+            Value::Quantity(..) => Err(String::from("Complex numbers cannot have units")),
+            Value::List(_) => Err(not_a_number()),
+            Value::Formula(_) => Err(unevaluated()),
+            x => Ok((x.to_f64(), 0.0))
+        }
+    }
+
+    pub fn polar(&self) -> Result<(f64, f64), String> {
+        let (re, im) = self.rect()?;
+        Ok((re.hypot(im), im.atan2(re)))
+    }
+
+    fn plain(&self, name: &str) -> Result<(f64, f64), String> {
+        match self {
+            Value::Quantity(_, unit) => Err(format!("{} needs a plain number, got {}", name, unit)),
+            x => x.rect()
+        }
+    }
+
+    // Checks that both sides measure the same thing and gives the size of
+    // `self` counted in `target`, as in `100 km/h to m/s`.
+    pub fn convert(&self, target: &Value) -> Result<f64, String> {
+        if let (Value::List(_), _) | (_, Value::List(_)) = (self, target) {
+            return Err(String::from("Only single quantities can be converted"));
+        }
+        let ((x, a), (y, b)) = (self.parts(), target.parts());
+        if a != b {
+            return Err(format!("Cannot convert {} to {}", a.describe(), b.describe()));
+        }
+        if y == 0.0 {
+            return Err(String::from("Division by zero"));
+        }
+        Ok(x / y)
+    }
+
+    pub fn and(self, other: Value) -> Result<Value, String> {
+        Ok(Value::Int(self.integer()? & other.integer()?))
+    }
+
+    pub fn or(self, other: Value) -> Result<Value, String> {
+        Ok(Value::Int(self.integer()? | other.integer()?))
+    }
+
+    pub fn xor(self, other: Value) -> Result<Value, String> {
+        Ok(Value::Int(self.integer()? ^ other.integer()?))
+    }
+
+    pub fn not(self) -> Result<Value, String> {
+        Ok(Value::Int(!self.integer()?))
+    }
+
+    pub fn shift_left(self, other: Value) -> Result<Value, String> {
+        let (x, n) = (self.integer()?, shift(&other, 128)?);
+        match x << n {
+            shifted if shifted >> n == x => Ok(Value::Int(shifted)),
+            _ => Err(overflow())
+        }
+    }
+
+    pub fn shift_right(self, other: Value) -> Result<Value, String> {
+        let (x, n) = (self.integer()?, shift(&other, 128)?);
+        Ok(Value::Int(x >> n))
+    }
+
+    pub fn integer(&self) -> Result<i128, String> {
+        match self {
+            Value::Int(x) => Ok(*x),
+            _ => Err(String::from("Bitwise operators need integers"))
+        }
+    }
+
+    pub fn neg(self) -> Result<Value, String> {
+        match self {
+            Value::Int(x) => x.checked_neg().map(Value::Int).ok_or_else(overflow),
+            Value::Real(x) => Ok(Value::Real(-x)),
+            Value::Decimal(x) => Ok(Value::Decimal(x.neg())),
+            Value::Quantity(x, unit) => Ok(Value::Quantity(-x, unit)),
+            Value::Complex(re, im) => Ok(Value::Complex(-re, -im)),
+            Value::List(items) => items.into_iter().map(Value::neg).collect::<Result<_, _>>().map(Value::List),
+            Value::Formula(_) => Err(unevaluated())
+        }
+    }
+
+    // Lists have no single size; the arithmetic above deals with them
+    // before ever asking for one.
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Value::Int(x) => *x as f64,
+            Value::Decimal(x) => x.to_f64(),
+            Value::Real(x) | Value::Quantity(x, _) | Value::Complex(x, _) => *x,
+            Value::List(_) | Value::Formula(_) => f64::NAN
+        }
+    }
+
+    fn parts(&self) -> (f64, Dimension) {
+        match self {
+            Value::Quantity(x, unit) => (*x, *unit),
+            x => (x.to_f64(), Dimension::default())
+        }
+    }
+}
+
+fn not_a_number() -> String {
+    String::from("Expected a number, got a list")
+}
+
+fn unevaluated() -> String {
+    String::from("Cannot calculate with a formula until its variables have values")
+}
+
+// An imaginary part that cancels out leaves a real number behind.
+pub fn complex(re: f64, im: f64) -> Result<Value, String> {
+    match (real(re)?, real(im)?) {
+        _ if im == 0.0 => Ok(Value::Real(re)),
+        _ => Ok(Value::Complex(re, im))
+    }
+}
+
+// Units that cancel out leave a plain number behind.
+fn quantity(x: f64, unit: Dimension) -> Result<Value, String> {
+    match real(x)? {
+        _ if unit.is_none() => Ok(Value::Real(x)),
+        _ => Ok(Value::Quantity(x, unit))
+    }
+}
+
+fn real(x: f64) -> Result<Value, String> {
+    if x.is_finite() {
+        Ok(Value::Real(x))
+    } else {
+        Err(String::from("Result is not a finite number"))
+    }
+}
+
+pub fn shift(amount: &Value, bits: u32) -> Result<u32, String> {
+    match amount.integer()? {
+        n if n >= 0 && n < bits as i128 => Ok(n as u32),
+        n => Err(format!("Shift amount out of range: {}", n))
+    }
+}
+
+pub fn overflow() -> String {
+    String::from("Integer overflow")
+}
+
+// The exact form that reads back in as the same value, which is what the
+// session file relies on; use `format::Format` for what the user sees.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(x) => write!(f, "{}", x),
+            Value::Real(x) => write!(f, "{:?}", x),
+            Value::Decimal(x) => write!(f, "{}", x),
+            Value::Quantity(x, unit) => write!(f, "{:?} {}", x, unit),
+            Value::Complex(re, im) => write!(f, "{:?}{}{:?}i", re, if im.is_sign_negative() { '-' } else { '+' }, im.abs()),
+            Value::List(items) => {
+                let items: Vec<String> = items.iter().map(|x| x.to_string()).collect();
+                write!(f, "[{}]", items.join(", "))
+            },
+            Value::Formula(tree) => write!(f, "{}", tree)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn length() -> Dimension {
+        crate::units::lookup("m").unwrap().1
+    }
+
+    #[test]
+    fn mixed_arithmetic() {
+        assert_eq!(Value::Int(1).add(Value::Real(0.5)), Ok(Value::Real(1.5)));
+        assert_eq!(Value::Int(7).div(Value::Int(2)), Ok(Value::Int(3)));
+        assert_eq!(Value::Real(7.0).div(Value::Int(2)), Ok(Value::Real(3.5)));
+        assert_eq!(Value::Real(2.5).neg(), Ok(Value::Real(-2.5)));
+    }
+
+    #[test]
+    fn arithmetic_errors() {
+        assert_eq!(Value::Int(i128::MAX).add(Value::Int(1)), Err(String::from("Integer overflow")));
+        assert_eq!(Value::Real(1.0).div(Value::Int(0)), Err(String::from("Division by zero")));
+        assert!(Value::Real(1e300).mul(Value::Real(1e300)).is_err());
+    }
+
+    #[test]
+    fn bitwise() {
+        assert_eq!(Value::Int(0b1100).and(Value::Int(0b1010)), Ok(Value::Int(0b1000)));
+        assert_eq!(Value::Int(0b1100).xor(Value::Int(0b1010)), Ok(Value::Int(0b0110)));
+        assert_eq!(Value::Int(5).not(), Ok(Value::Int(-6)));
+        assert_eq!(Value::Int(1).shift_left(Value::Int(4)), Ok(Value::Int(16)));
+        assert_eq!(Value::Int(-16).shift_right(Value::Int(2)), Ok(Value::Int(-4)));
+        assert!(Value::Int(1).shift_left(Value::Int(128)).is_err());
+        assert!(Value::Int(1).shift_left(Value::Int(-1)).is_err());
+        assert!(Value::Int(i128::MAX).shift_left(Value::Int(1)).is_err());
+        assert!(Value::Real(1.0).or(Value::Int(1)).is_err());
+    }
+
+    #[test]
+    fn fixed_width() {
+        let byte = Width::parse("u8").unwrap();
+        assert_eq!(byte.wrap(256 + 7), 7);
+        assert_eq!(byte.wrap(-1), 255);
+        let signed = Width::parse("i8").unwrap();
+        assert_eq!(signed.wrap(128), -128);
+        assert_eq!(signed.wrap(-129), 127);
+        assert_eq!(Width::parse("u64").unwrap().wrap(-1), u64::MAX as i128);
+        assert_eq!(Width::parse("i64").unwrap().name(), "i64");
+        assert_eq!(Width::parse("u12"), None);
+    }
+
+    #[test]
+    fn quantities() {
+        let length = length();
+        let metre = || Value::Quantity(1.0, length);
+        let second = || Value::Quantity(1.0, crate::units::lookup("s").unwrap().1);
+        assert_eq!(Value::Int(3).mul(metre()).and_then(|x| x.add(Value::Quantity(0.2, length))), Ok(Value::Quantity(3.2, length)));
+        assert_eq!(metre().add(second()), Err(String::from("Cannot add m and s")));
+        assert_eq!(metre().sub(Value::Int(1)), Err(String::from("Cannot subtract a plain number from m")));
+        assert_eq!(metre().div(metre()), Ok(Value::Real(1.0)));
+        assert_eq!(metre().div(second()).unwrap().to_string(), "1.0 m/s");
+        assert_eq!(metre().pow(Value::Int(2)).and_then(|area| area.pow(Value::Real(0.5))), Ok(metre()));
+        assert!(Value::Int(2).pow(metre()).is_err());
+        assert_eq!(Value::Quantity(1000.0, length).convert(&metre()), Ok(1000.0));
+        assert_eq!(metre().convert(&second()), Err(String::from("Cannot convert m to s")));
+    }
+
+    #[test]
+    fn complex_arithmetic() {
+        let z = || Value::Complex(3.0, 4.0);
+        assert_eq!(z().abs(), Ok(Value::Real(5.0)));
+        assert_eq!(z().mul(z().conj().unwrap()), Ok(Value::Real(25.0)));
+        assert_eq!(z().div(Value::Complex(0.0, 1.0)), Ok(Value::Complex(4.0, -3.0)));
+        assert_eq!(z().sub(Value::Complex(0.0, 4.0)), Ok(Value::Real(3.0)));
+        assert_eq!(Value::Int(-1).sqrt(), Ok(Value::Complex(0.0, 1.0)));
+        assert_eq!(Value::Int(4).sqrt(), Ok(Value::Real(2.0)));
+        assert_eq!(z().re().and_then(|re| re.add(z().im().unwrap())), Ok(Value::Real(7.0)));
+        assert!(z().add(Value::Quantity(1.0, length())).is_err());
+        assert!(z().div(Value::Complex(0.0, 0.0)).is_err());
+    }
+
+    #[test]
+    fn lists() {
+        let list = |items: &[i128]| Value::List(items.iter().map(|x| Value::Int(*x)).collect());
+        assert_eq!(list(&[1, 2, 3]).add(list(&[10, 20, 30])), Ok(list(&[11, 22, 33])));
+        assert_eq!(list(&[1, 2]).mul(Value::Int(3)), Ok(list(&[3, 6])));
+        assert_eq!(Value::Int(12).div(list(&[2, 3])), Ok(list(&[6, 4])));
+        assert_eq!(list(&[1, 2]).sub(list(&[1, 2, 3])), Err(String::from("Lists have different lengths: 2 and 3")));
+        assert_eq!(list(&[1, 0]).neg(), Ok(list(&[-1, 0])));
+        assert!(list(&[1, 0]).div(list(&[1, 0])).is_err());
+        assert_eq!(Value::List(vec![list(&[1, 2]), list(&[3, 4])]).to_string(), "[[1, 2], [3, 4]]");
+    }
+
+    #[test]
+    fn complex_functions() {
+        let close = |x: Value, re: f64, im: f64| {
+            let (a, b) = x.rect().unwrap();
+            (a - re).abs() < 1e-12 && (b - im).abs() < 1e-12
+        };
+        let pi = std::f64::consts::PI;
+        assert!(close(Value::Complex(0.0, pi).exp().unwrap(), -1.0, 0.0));
+        assert!(close(Value::Int(-1).ln().unwrap(), 0.0, pi));
+        assert!(close(Value::Int(1000).log().unwrap(), 3.0, 0.0));
+        assert!(close(Value::Real(pi / 6.0).sin().unwrap(), 0.5, 0.0));
+        assert!(close(Value::Real(pi / 3.0).cos().unwrap(), 0.5, 0.0));
+        assert!(close(Value::Real(pi / 4.0).tan().unwrap(), 1.0, 0.0));
+        assert!(close(Value::Complex(0.0, 1.0).sin().unwrap(), 0.0, 1f64.sinh()));
+        assert_eq!(Value::Complex(0.0, 1.0).pow(Value::Int(2)), Ok(Value::Real(-1.0)));
+        assert!(close(Value::Complex(0.0, 1.0).pow(Value::Real(0.5)).unwrap(), 0.5f64.sqrt(), 0.5f64.sqrt()));
+        assert!(close(Value::Int(-8).pow(Value::Real(1.0 / 3.0)).unwrap(), 1.0, 3f64.sqrt()));
+        assert_eq!(Value::Complex(0.0, 1.0).arg(), Ok(Value::Real(pi / 2.0)));
+        assert_eq!(Value::Complex(1.0, -2.0).to_string(), "1.0-2.0i");
+    }
+
+    #[test]
+    fn powers() {
+        assert_eq!(Value::Int(2).pow(Value::Int(10)), Ok(Value::Int(1024)));
+        assert_eq!(Value::Int(2).pow(Value::Int(-1)), Ok(Value::Real(0.5)));
+        assert_eq!(Value::Int(-1).pow(Value::Int(1 << 40)), Ok(Value::Int(1)));
+        assert_eq!(Value::Int(2).pow(Value::Int(200)), Err(String::from("Integer overflow")));
+        assert_eq!(Value::Real(4.0).pow(Value::Real(0.5)), Ok(Value::Real(2.0)));
+    }
+
+    #[test]
+    fn exact_display() {
+        assert_eq!(Value::Int(-4).to_string(), "-4");
+        assert_eq!(Value::Real(2.0).to_string(), "2.0");
+        assert_eq!(Value::Real(1e300).to_string(), "1e300");
+        assert_eq!(Value::Real(0.1).to_string(), "0.1");
+    }
+}